@@ -7,16 +7,10 @@ use near_sdk::json_types::U128;
 use near_sdk::{env, log, near, require, AccountId, BorshStorageKey, PanicOnDefault, Promise, PromiseOrValue};
 use near_sdk::NearToken;
 
-use near_sdk::{PublicKey, CurveType};
-use std::panic::{catch_unwind, AssertUnwindSafe};
-use near_sdk::serde_json::json;
-
-
-
-/// Mint price : 0.01 NEAR
+/// Initial mint price : 0.01 NEAR
 const MINT_PRICE: NearToken = NearToken::from_yoctonear(10u128.pow(22));
 
-/// Quantity of tokens per call : 1000 * 10^décimales
+/// Initial quantity of tokens per call : 1000 * 10^décimales
 const MINT_AMOUNT: u128 = 1000u128 * 10u128.pow(24);
 
 const DATA_IMAGE_SVG_NEAR_ICON: &str = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 288 288'%3E%3Cg id='l' data-name='l'%3E%3Cpath d='M187.58,79.81l-30.1,44.69a3.2,3.2,0,0,0,4.75,4.2L191.86,103a1.2,1.2,0,0,1,2,.91v80.46a1.2,1.2,0,0,1-2.12.77L102.18,77.93A15.35,15.35,0,0,0,90.47,72.5H87.34A15.34,15.34,0,0,0,72,87.84V201.16A15.34,15.34,0,0,0,87.34,216.5h0a15.35,15.35,0,0,0,13.08-7.31l30.1-44.69a3.2,3.2,0,0,0-4.75-4.2L96.14,186a1.2,1.2,0,0,1-2-.91V104.61a1.2,1.2,0,0,1,2.12-.77l89.55,107.23a15.35,15.35,0,0,0,11.71,5.43h3.13A15.34,15.34,0,0,0,216,201.16V87.84A15.34,15.34,0,0,0,200.66,72.5h0A15.35,15.35,0,0,0,187.58,79.81Z'/%3E%3C/g%3E%3C/svg%3E";
@@ -27,6 +21,34 @@ pub struct Contract {
     owner: AccountId,
     token: FungibleToken,
     metadata: LazyOption<FungibleTokenMetadata>,
+    mint_price: NearToken,
+    mint_amount: u128,
+}
+
+/// State layout deployed before the mint price and amount were stored on chain
+#[near(serializers = [borsh])]
+struct OldContract {
+    owner: AccountId,
+    token: FungibleToken,
+    metadata: LazyOption<FungibleTokenMetadata>,
+}
+
+/// Mint economics read by the dapp
+#[near(serializers = [json])]
+pub struct MintConfig {
+    pub price: NearToken,
+    pub amount: U128,
+    pub decimals: u8,
+    pub owner: AccountId,
+}
+
+/// NEP-297 events emitted when the owner changes the mint economics
+#[near(event_json(standard = "iabs"))]
+pub enum IabsEvent {
+    #[event_version("1.0.0")]
+    MintPriceUpdated { old_price: NearToken, new_price: NearToken },
+    #[event_version("1.0.0")]
+    MintAmountUpdated { old_amount: U128, new_amount: U128 },
 }
 
 #[derive(BorshSerialize, BorshStorageKey)]
@@ -63,6 +85,8 @@ impl Contract {
             owner: owner_id.clone(),
             token: FungibleToken::new(StorageKey::FungibleToken),
             metadata: LazyOption::new(StorageKey::Metadata, Some(&metadata)),
+            mint_price: MINT_PRICE,
+            mint_amount: MINT_AMOUNT,
         };
         this.token.internal_register_account(&owner_id);
         this.token.internal_deposit(&owner_id, total_supply.into());
@@ -77,29 +101,67 @@ impl Contract {
         this
     }
 
+    /// Upgrade state written by the previous code, starting the mint price and
+    /// amount at the values it had hardcoded. Call once right after redeploying.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let old: OldContract =
+            env::state_read().unwrap_or_else(|| env::panic_str("No state to migrate"));
+        Self {
+            owner: old.owner,
+            token: old.token,
+            metadata: old.metadata,
+            mint_price: MINT_PRICE,
+            mint_amount: MINT_AMOUNT,
+        }
+    }
+
     pub fn ft_metadata(&self) -> FungibleTokenMetadata {
         self.metadata.get().unwrap()
     }
 
-    /// Mint against 0.01 NEAR
+    /// Mint against `mint_price`, plus the storage cost of registering a new account.
+    /// Anything attached above that is refunded.
     #[payable]
     pub fn mint(&mut self) {
         let deposit = env::attached_deposit();
-        require!(deposit >= MINT_PRICE, "Insufficient payment, need at least 0.01 NEAR");
         let caller = env::predecessor_account_id();
 
+        let is_registered = self.token.accounts.contains_key(&caller);
+        let storage_cost = if is_registered {
+            NearToken::from_yoctonear(0)
+        } else {
+            self.token.storage_balance_bounds().min
+        };
+        let required = self.mint_price.saturating_add(storage_cost);
+        require!(
+            deposit >= required,
+            format!(
+                "Insufficient payment, need at least {} yoctoNEAR ({} yoctoNEAR mint price + {} yoctoNEAR account storage), attached {} yoctoNEAR",
+                required.as_yoctonear(),
+                self.mint_price.as_yoctonear(),
+                storage_cost.as_yoctonear(),
+                deposit.as_yoctonear()
+            )
+        );
 
-        if !self.token.accounts.contains_key(&caller) {
+        if !is_registered {
             self.token.internal_register_account(&caller);
         }
 
-        self.token.internal_deposit(&caller, MINT_AMOUNT);
+        self.token.internal_deposit(&caller, self.mint_amount);
         near_contract_standards::fungible_token::events::FtMint {
             owner_id: &caller,
-            amount: U128(MINT_AMOUNT),
+            amount: U128(self.mint_amount),
             memo: Some("mint via payment"),
         }
         .emit();
+
+        let refund = deposit.saturating_sub(required);
+        if !refund.is_zero() {
+            Promise::new(caller).transfer(refund);
+        }
     }
 
     /// Withdraw tout le solde du contrat vers l'owner
@@ -127,6 +189,43 @@ impl Contract {
     pub fn get_owner(&self) -> AccountId {
         self.owner.clone()
     }
+
+    /// Change the price of a mint (owner only)
+    pub fn set_mint_price(&mut self, price: NearToken) {
+        let caller = env::predecessor_account_id();
+        require!(caller == self.owner, "Only owner can set the mint price");
+
+        let old_price = self.mint_price;
+        self.mint_price = price;
+        IabsEvent::MintPriceUpdated {
+            old_price,
+            new_price: price,
+        }
+        .emit();
+    }
+
+    /// Change the quantity of tokens per mint (owner only)
+    pub fn set_mint_amount(&mut self, amount: U128) {
+        let caller = env::predecessor_account_id();
+        require!(caller == self.owner, "Only owner can set the mint amount");
+
+        let old_amount = U128(self.mint_amount);
+        self.mint_amount = amount.0;
+        IabsEvent::MintAmountUpdated {
+            old_amount,
+            new_amount: amount,
+        }
+        .emit();
+    }
+
+    pub fn get_mint_config(&self) -> MintConfig {
+        MintConfig {
+            price: self.mint_price,
+            amount: U128(self.mint_amount),
+            decimals: self.ft_metadata().decimals,
+            owner: self.owner.clone(),
+        }
+    }
 }
 
 #[near]
@@ -175,6 +274,50 @@ impl FungibleTokenResolver for Contract {
     }
 }
 
+#[near]
+impl StorageManagement for Contract {
+    #[payable]
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        self.token.storage_deposit(account_id, registration_only)
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<NearToken>) -> StorageBalance {
+        self.token.storage_withdraw(amount)
+    }
+
+    /// Unregister the caller; with `force`, any remaining tokens are burned
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        if let Some((account_id, balance)) = self.token.internal_storage_unregister(force) {
+            log!("Closed @{} with {}", account_id, balance);
+            if balance > 0 {
+                near_contract_standards::fungible_token::events::FtBurn {
+                    owner_id: &account_id,
+                    amount: U128(balance),
+                    memo: Some("account unregistered with force"),
+                }
+                .emit();
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        self.token.storage_balance_bounds()
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.token.storage_balance_of(account_id)
+    }
+}
+
 
 
 #[cfg(test)]
@@ -182,7 +325,6 @@ mod tests {
     use super::*;
     use near_sdk::test_utils::VMContextBuilder;
     use near_sdk::{testing_env, AccountId, NearToken, PublicKey, CurveType};
-    use near_sdk::serde_json::json;
     use near_sdk::json_types::U128;
     use std::panic::AssertUnwindSafe;
 
@@ -239,8 +381,9 @@ mod tests {
         );
         assert_eq!(contract.ft_total_supply().0, 1_000_000);
 
-        // Mint OK
-        let ctx2 = get_context("bob.testnet", MINT_PRICE, INITIAL_BALANCE);
+        // Mint OK (prix + stockage du compte)
+        let storage_cost = contract.storage_balance_bounds().min;
+        let ctx2 = get_context("bob.testnet", MINT_PRICE.saturating_add(storage_cost), INITIAL_BALANCE);
         testing_env!(ctx2.build());
         contract.mint();
 
@@ -256,6 +399,61 @@ mod tests {
         assert!(res.is_err());
     }
 
+    fn transfers_to(receiver: &str) -> Vec<NearToken> {
+        near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .filter(|receipt| receipt.receiver_id.as_str() == receiver)
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                near_sdk::mock::MockAction::Transfer { deposit, .. } => Some(deposit),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_mint_charges_storage_and_refunds_excess() {
+        let ctx = get_context("alice.testnet", NearToken::from_yoctonear(0), INITIAL_BALANCE);
+        testing_env!(ctx.build());
+        let mut contract = Contract::new_default_meta(
+            "alice.testnet".parse::<AccountId>().unwrap(),
+            U128(0),
+        );
+        let storage_cost = contract.storage_balance_bounds().min;
+
+        // Le prix seul ne couvre pas l'enregistrement du compte
+        let ctx = get_context("bob.testnet", MINT_PRICE, NearToken::from_near(10));
+        testing_env!(ctx.build());
+        let res = std::panic::catch_unwind(AssertUnwindSafe(|| contract.mint()));
+        assert!(res.is_err());
+        assert!(contract.storage_balance_of("bob.testnet".parse::<AccountId>().unwrap()).is_none());
+
+        // Premier mint : prix + stockage retenus, l'excédent est remboursé
+        let ctx = get_context("bob.testnet", NearToken::from_near(1), NearToken::from_near(10));
+        testing_env!(ctx.build());
+        contract.mint();
+        assert_eq!(
+            contract.ft_balance_of("bob.testnet".parse::<AccountId>().unwrap()).0,
+            MINT_AMOUNT
+        );
+        assert_eq!(
+            transfers_to("bob.testnet"),
+            vec![NearToken::from_near(1)
+                .saturating_sub(MINT_PRICE)
+                .saturating_sub(storage_cost)]
+        );
+
+        // Compte déjà enregistré : seul le prix est retenu, sans remboursement au prix exact
+        let ctx = get_context("bob.testnet", MINT_PRICE, NearToken::from_near(10));
+        testing_env!(ctx.build());
+        contract.mint();
+        assert_eq!(
+            contract.ft_balance_of("bob.testnet".parse::<AccountId>().unwrap()).0,
+            2 * MINT_AMOUNT
+        );
+        assert!(transfers_to("bob.testnet").is_empty());
+    }
+
     #[test]
     fn test_withdraw() {
         let owner_id = "owner.testnet".parse::<AccountId>().unwrap();
@@ -282,4 +480,206 @@ mod tests {
         assert!(res.is_ok());
     }
 
+    #[test]
+    fn test_storage_balance_bounds() {
+        let ctx = get_context("alice.testnet", NearToken::from_yoctonear(0), INITIAL_BALANCE);
+        testing_env!(ctx.build());
+        let contract = Contract::new_default_meta(
+            "alice.testnet".parse::<AccountId>().unwrap(),
+            U128(1_000_000),
+        );
+
+        // Le stockage d'un compte a un coût fixe : min == max
+        let bounds = contract.storage_balance_bounds();
+        assert!(bounds.min > NearToken::from_yoctonear(0));
+        assert_eq!(bounds.max, Some(bounds.min));
+    }
+
+    #[test]
+    fn test_storage_deposit_and_balance_of() {
+        let bob = "bob.testnet".parse::<AccountId>().unwrap();
+        let ctx = get_context("alice.testnet", NearToken::from_yoctonear(0), INITIAL_BALANCE);
+        testing_env!(ctx.build());
+        let mut contract = Contract::new_default_meta(
+            "alice.testnet".parse::<AccountId>().unwrap(),
+            U128(1_000_000),
+        );
+        let min = contract.storage_balance_bounds().min;
+        assert!(contract.storage_balance_of(bob.clone()).is_none());
+
+        // Dépôt insuffisant
+        let ctx = get_context("bob.testnet", min.saturating_sub(NearToken::from_yoctonear(1)), INITIAL_BALANCE);
+        testing_env!(ctx.build());
+        let res = std::panic::catch_unwind(AssertUnwindSafe(|| contract.storage_deposit(None, None)));
+        assert!(res.is_err());
+
+        // Enregistrement de bob par alice, l'excédent est remboursé
+        let ctx = get_context("alice.testnet", min.saturating_mul(2), INITIAL_BALANCE);
+        testing_env!(ctx.build());
+        let balance = contract.storage_deposit(Some(bob.clone()), Some(true));
+        assert_eq!(balance.total, min);
+        assert_eq!(balance.available, NearToken::from_yoctonear(0));
+
+        let balance = contract.storage_balance_of(bob.clone()).unwrap();
+        assert_eq!(balance.total, min);
+
+        // ft_transfer vers bob fonctionne une fois enregistré
+        let ctx = get_context("alice.testnet", NearToken::from_yoctonear(1), INITIAL_BALANCE);
+        testing_env!(ctx.build());
+        contract.ft_transfer(bob.clone(), U128(100), None);
+        assert_eq!(contract.ft_balance_of(bob).0, 100);
+    }
+
+    #[test]
+    fn test_storage_withdraw() {
+        let ctx = get_context("alice.testnet", NearToken::from_yoctonear(0), INITIAL_BALANCE);
+        testing_env!(ctx.build());
+        let mut contract = Contract::new_default_meta(
+            "alice.testnet".parse::<AccountId>().unwrap(),
+            U128(1_000_000),
+        );
+        let min = contract.storage_balance_bounds().min;
+
+        // Compte non enregistré
+        let ctx = get_context("bob.testnet", NearToken::from_yoctonear(1), INITIAL_BALANCE);
+        testing_env!(ctx.build());
+        let res = std::panic::catch_unwind(AssertUnwindSafe(|| contract.storage_withdraw(None)));
+        assert!(res.is_err());
+
+        let ctx = get_context("bob.testnet", min, INITIAL_BALANCE);
+        testing_env!(ctx.build());
+        contract.storage_deposit(None, None);
+
+        // Rien n'est disponible au-delà du minimum
+        let ctx = get_context("bob.testnet", NearToken::from_yoctonear(1), INITIAL_BALANCE);
+        testing_env!(ctx.build());
+        let balance = contract.storage_withdraw(None);
+        assert_eq!(balance.total, min);
+        let res = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            contract.storage_withdraw(Some(NearToken::from_yoctonear(1)))
+        }));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_storage_unregister() {
+        let bob = "bob.testnet".parse::<AccountId>().unwrap();
+        // Le contrat doit pouvoir rembourser le dépôt de stockage
+        let balance = NearToken::from_near(10);
+        let ctx = get_context("alice.testnet", NearToken::from_yoctonear(0), balance);
+        testing_env!(ctx.build());
+        let mut contract = Contract::new_default_meta(
+            "alice.testnet".parse::<AccountId>().unwrap(),
+            U128(1_000_000),
+        );
+        let min = contract.storage_balance_bounds().min;
+
+        // Compte inconnu
+        let ctx = get_context("bob.testnet", NearToken::from_yoctonear(1), balance);
+        testing_env!(ctx.build());
+        assert!(!contract.storage_unregister(None));
+
+        let ctx = get_context("bob.testnet", min, balance);
+        testing_env!(ctx.build());
+        contract.storage_deposit(None, None);
+        let ctx = get_context("alice.testnet", NearToken::from_yoctonear(1), balance);
+        testing_env!(ctx.build());
+        contract.ft_transfer(bob.clone(), U128(100), None);
+
+        // Solde positif sans force
+        let ctx = get_context("bob.testnet", NearToken::from_yoctonear(1), balance);
+        testing_env!(ctx.build());
+        let res = std::panic::catch_unwind(AssertUnwindSafe(|| contract.storage_unregister(None)));
+        assert!(res.is_err());
+
+        // Avec force, les tokens restants sont brûlés
+        assert!(contract.storage_unregister(Some(true)));
+        assert!(contract.storage_balance_of(bob.clone()).is_none());
+        assert_eq!(contract.ft_balance_of(bob).0, 0);
+        assert_eq!(contract.ft_total_supply().0, 1_000_000 - 100);
+    }
+
+    #[test]
+    fn test_mint_config() {
+        let ctx = get_context("alice.testnet", NearToken::from_yoctonear(0), INITIAL_BALANCE);
+        testing_env!(ctx.build());
+        let mut contract = Contract::new_default_meta(
+            "alice.testnet".parse::<AccountId>().unwrap(),
+            U128(0),
+        );
+
+        let config = contract.get_mint_config();
+        assert_eq!(config.price, MINT_PRICE);
+        assert_eq!(config.amount, U128(MINT_AMOUNT));
+        assert_eq!(config.decimals, 24);
+        assert_eq!(config.owner, "alice.testnet".parse::<AccountId>().unwrap());
+
+        // Seul l'owner peut modifier le prix et la quantité
+        let ctx = get_context("mallory.testnet", NearToken::from_yoctonear(0), INITIAL_BALANCE);
+        testing_env!(ctx.build());
+        let res = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            contract.set_mint_price(NearToken::from_yoctonear(1))
+        }));
+        assert!(res.is_err());
+        let res = std::panic::catch_unwind(AssertUnwindSafe(|| contract.set_mint_amount(U128(1))));
+        assert!(res.is_err());
+
+        let ctx = get_context("alice.testnet", NearToken::from_yoctonear(0), INITIAL_BALANCE);
+        testing_env!(ctx.build());
+        contract.set_mint_price(NearToken::from_millinear(50));
+        contract.set_mint_amount(U128(42));
+        assert_eq!(
+            near_sdk::test_utils::get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"iabs","version":"1.0.0","event":"mint_price_updated","data":{"old_price":"10000000000000000000000","new_price":"50000000000000000000000"}}"#,
+                r#"EVENT_JSON:{"standard":"iabs","version":"1.0.0","event":"mint_amount_updated","data":{"old_amount":"1000000000000000000000000000","new_amount":"42"}}"#,
+            ]
+        );
+
+        let config = contract.get_mint_config();
+        assert_eq!(config.price, NearToken::from_millinear(50));
+        assert_eq!(config.amount, U128(42));
+
+        // Le mint utilise la nouvelle configuration
+        let storage_cost = contract.storage_balance_bounds().min;
+        let ctx = get_context("bob.testnet", MINT_PRICE.saturating_add(storage_cost), NearToken::from_near(10));
+        testing_env!(ctx.build());
+        let res = std::panic::catch_unwind(AssertUnwindSafe(|| contract.mint()));
+        assert!(res.is_err());
+
+        let ctx = get_context(
+            "bob.testnet",
+            NearToken::from_millinear(50).saturating_add(storage_cost),
+            NearToken::from_near(10),
+        );
+        testing_env!(ctx.build());
+        contract.mint();
+        assert_eq!(
+            contract.ft_balance_of("bob.testnet".parse::<AccountId>().unwrap()).0,
+            42
+        );
+    }
+
+    #[test]
+    fn test_migrate() {
+        // État écrit par l'ancien code, sans prix ni quantité de mint
+        let alice = "alice.testnet".parse::<AccountId>().unwrap();
+        let ctx = get_context("contract.testnet", NearToken::from_yoctonear(0), INITIAL_BALANCE);
+        testing_env!(ctx.build());
+        let mut token = FungibleToken::new(StorageKey::FungibleToken);
+        token.internal_register_account(&alice);
+        token.internal_deposit(&alice, 1_000_000);
+        env::state_write(&OldContract {
+            owner: alice.clone(),
+            token,
+            metadata: LazyOption::new(StorageKey::Metadata, None),
+        });
+
+        let contract = Contract::migrate();
+        assert_eq!(contract.owner, alice);
+        assert_eq!(contract.ft_balance_of(alice).0, 1_000_000);
+        assert_eq!(contract.ft_total_supply().0, 1_000_000);
+        assert_eq!(contract.mint_price, MINT_PRICE);
+        assert_eq!(contract.mint_amount, MINT_AMOUNT);
+    }
 }
@@ -0,0 +1,314 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+#[ink::contract]
+mod iabs_factory {
+    use iabs_token::IabsTokenRef;
+    use ink::env::call::{build_create, ExecutionInput, Selector};
+    use ink::prelude::{string::String, vec::Vec};
+    use ink::storage::Mapping;
+
+    /// A token deployed through the factory
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub struct TokenRecord {
+        pub token: AccountId,
+        pub creator: AccountId,
+        pub salt: [u8; 32],
+    }
+
+    #[ink(storage)]
+    pub struct IabsFactory {
+        /// Address of the factory owner
+        owner: AccountId,
+        /// Code hash of the uploaded `IabsToken` contract
+        token_code_hash: Hash,
+        /// Deployed tokens, indexed by creation order
+        tokens: Mapping<u32, TokenRecord>,
+        /// Number of deployed tokens
+        token_count: u32,
+    }
+
+    #[ink(event)]
+    pub struct TokenCreated {
+        #[ink(topic)]
+        token: AccountId,
+        #[ink(topic)]
+        creator: AccountId,
+        salt: [u8; 32],
+    }
+
+    #[ink(event)]
+    pub struct TokenCodeHashUpdated {
+        #[ink(topic)]
+        old: Hash,
+        #[ink(topic)]
+        new: Hash,
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum Error {
+        NotOwner,
+        InstantiationFailed,
+        OwnershipTransferFailed,
+        Overflow,
+    }
+
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl IabsFactory {
+        /// Constructor deploying tokens from the given `IabsToken` code hash
+        #[ink(constructor)]
+        pub fn new(token_code_hash: Hash) -> Self {
+            Self {
+                owner: Self::env().caller(),
+                token_code_hash,
+                tokens: Mapping::default(),
+                token_count: 0,
+            }
+        }
+
+        /// Instantiate a new `IabsToken` and hand its ownership to the caller.
+        ///
+        /// The token address depends on the factory, the code hash, the
+        /// constructor arguments and `salt`, so reusing a salt with the same
+        /// arguments fails.
+        #[ink(message)]
+        pub fn create_token(
+            &mut self,
+            name: Option<String>,
+            symbol: Option<String>,
+            decimals: u8,
+            min_payment: Balance,
+            mint_amount: Balance,
+            salt: [u8; 32],
+        ) -> Result<AccountId> {
+            let creator = self.env().caller();
+            let index = self.token_count;
+            let token_count = index.checked_add(1).ok_or(Error::Overflow)?;
+
+            let mut token = match build_create::<IabsTokenRef>()
+                .code_hash(self.token_code_hash)
+                .endowment(0)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(ink::selector_bytes!("new_with_config")))
+                        .push_arg(name)
+                        .push_arg(symbol)
+                        .push_arg(decimals)
+                        .push_arg(min_payment)
+                        .push_arg(mint_amount),
+                )
+                .salt_bytes(salt)
+                .returns::<IabsTokenRef>()
+                .try_instantiate()
+            {
+                Ok(Ok(token)) => token,
+                _ => return Err(Error::InstantiationFailed),
+            };
+
+            // the factory is the token owner right after instantiation
+            token
+                .transfer_ownership(creator)
+                .map_err(|_| Error::OwnershipTransferFailed)?;
+
+            let address = ink::ToAccountId::to_account_id(&token);
+            self.tokens.insert(
+                index,
+                &TokenRecord {
+                    token: address,
+                    creator,
+                    salt,
+                },
+            );
+            self.token_count = token_count;
+
+            self.env().emit_event(TokenCreated {
+                token: address,
+                creator,
+                salt,
+            });
+
+            Ok(address)
+        }
+
+        /// Up to `limit` deployed tokens, starting at index `offset`
+        #[ink(message)]
+        pub fn list_tokens(&self, offset: u32, limit: u32) -> Vec<TokenRecord> {
+            let end = offset.saturating_add(limit).min(self.token_count);
+            (offset..end)
+                .filter_map(|index| self.tokens.get(index))
+                .collect()
+        }
+
+        #[ink(message)]
+        pub fn token(&self, index: u32) -> Option<TokenRecord> {
+            self.tokens.get(index)
+        }
+
+        #[ink(message)]
+        pub fn token_count(&self) -> u32 {
+            self.token_count
+        }
+
+        #[ink(message)]
+        pub fn token_code_hash(&self) -> Hash {
+            self.token_code_hash
+        }
+
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        /// Allow owner to point new deployments at another `IabsToken` code hash
+        #[ink(message)]
+        pub fn set_token_code_hash(&mut self, new_token_code_hash: Hash) -> Result<()> {
+            let caller = self.env().caller();
+
+            if caller != self.owner {
+                return Err(Error::NotOwner);
+            }
+
+            let old = self.token_code_hash;
+            self.token_code_hash = new_token_code_hash;
+            self.env().emit_event(TokenCodeHashUpdated {
+                old,
+                new: new_token_code_hash,
+            });
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn new_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let factory = IabsFactory::new(Hash::from([0x01; 32]));
+
+            assert_eq!(factory.owner(), accounts.alice);
+            assert_eq!(factory.token_code_hash(), Hash::from([0x01; 32]));
+            assert_eq!(factory.token_count(), 0);
+            assert_eq!(factory.token(0), None);
+            assert_eq!(factory.list_tokens(0, 10), Vec::new());
+            assert_eq!(factory.list_tokens(u32::MAX, u32::MAX), Vec::new());
+        }
+
+        #[ink::test]
+        fn set_token_code_hash_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut factory = IabsFactory::new(Hash::from([0x01; 32]));
+
+            assert_eq!(factory.set_token_code_hash(Hash::from([0x02; 32])), Ok(()));
+            assert_eq!(factory.token_code_hash(), Hash::from([0x02; 32]));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                factory.set_token_code_hash(Hash::from([0x03; 32])),
+                Err(Error::NotOwner)
+            );
+            assert_eq!(factory.token_code_hash(), Hash::from([0x02; 32]));
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use iabs_token::IabsToken;
+        use ink_e2e::ContractsBackend;
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        #[ink_e2e::test]
+        async fn create_token_hands_over_ownership<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // given: a factory deploying the uploaded token code
+            let token_code_hash = client
+                .upload("iabs_token", &ink_e2e::alice())
+                .submit()
+                .await
+                .expect("upload failed")
+                .code_hash;
+            let mut constructor = IabsFactoryRef::new(token_code_hash);
+            let factory = client
+                .instantiate("iabs_factory", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = factory.call_builder::<IabsFactory>();
+            let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+            let charlie = ink_e2e::account_id(ink_e2e::AccountKeyring::Charlie);
+
+            // when: two creators deploy a token each
+            let create_token = call_builder.create_token(
+                Some(String::from("Bob Token")),
+                Some(String::from("BOB")),
+                18,
+                1,
+                1_000,
+                [0x01; 32],
+            );
+            let bob_token = client
+                .call(&ink_e2e::bob(), &create_token)
+                .submit()
+                .await
+                .expect("create_token by bob failed")
+                .return_value()
+                .expect("bob token not created");
+            let create_token = call_builder.create_token(
+                Some(String::from("Charlie Token")),
+                Some(String::from("CHARLIE")),
+                12,
+                2,
+                2_000,
+                [0x02; 32],
+            );
+            let charlie_token = client
+                .call(&ink_e2e::charlie(), &create_token)
+                .submit()
+                .await
+                .expect("create_token by charlie failed")
+                .return_value()
+                .expect("charlie token not created");
+
+            // then: each creator owns their token
+            let owner = ink_e2e::create_call_builder::<IabsToken>(bob_token).owner();
+            let owner = client
+                .call(&ink_e2e::alice(), &owner)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(owner, bob);
+
+            let owner = ink_e2e::create_call_builder::<IabsToken>(charlie_token).owner();
+            let owner = client
+                .call(&ink_e2e::alice(), &owner)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(owner, charlie);
+
+            // then: the registry pages in creation order
+            let list_tokens = call_builder.list_tokens(1, 1);
+            let tokens = client
+                .call(&ink_e2e::alice(), &list_tokens)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(
+                tokens,
+                vec![TokenRecord {
+                    token: charlie_token,
+                    creator: charlie,
+                    salt: [0x02; 32],
+                }]
+            );
+
+            Ok(())
+        }
+    }
+}
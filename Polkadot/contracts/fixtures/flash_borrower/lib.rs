@@ -0,0 +1,63 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+//! Minimal `FlashBorrower` used by the `flash_loan` tests, approving the
+//! repayment minus a configurable shortfall or rejecting every loan.
+
+pub use self::flash_borrower::{Borrower, BorrowerRef};
+
+#[ink::contract]
+pub mod flash_borrower {
+    use iabs_token::{FlashBorrower, FlashBorrowerError, PSP22};
+    use ink::prelude::{string::String, vec::Vec};
+
+    #[ink(storage)]
+    pub struct Borrower {
+        /// Whether loans are accepted
+        accept: bool,
+        /// Amount left out of the approved repayment
+        shortfall: Balance,
+        /// Balance held while the last accepted loan was out
+        borrowed: Balance,
+    }
+
+    impl Borrower {
+        #[ink(constructor)]
+        pub fn new(accept: bool, shortfall: Balance) -> Self {
+            Self {
+                accept,
+                shortfall,
+                borrowed: 0,
+            }
+        }
+
+        #[ink(message)]
+        pub fn borrowed(&self) -> Balance {
+            self.borrowed
+        }
+    }
+
+    impl FlashBorrower for Borrower {
+        #[ink(message)]
+        fn on_flashloan(
+            &mut self,
+            _initiator: AccountId,
+            token: AccountId,
+            amount: Balance,
+            fee: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), FlashBorrowerError> {
+            if !self.accept {
+                return Err(FlashBorrowerError::FlashloanRejected(String::from(
+                    "loans not accepted",
+                )));
+            }
+
+            let mut lender: ink::contract_ref!(PSP22) = token.into();
+            self.borrowed = lender.balance_of(self.env().account_id());
+            let repayment = amount.saturating_add(fee).saturating_sub(self.shortfall);
+            lender
+                .approve(token, repayment)
+                .map_err(|_| FlashBorrowerError::FlashloanRejected(String::from("approve failed")))
+        }
+    }
+}
@@ -0,0 +1,127 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+//! `IabsToken` as deployed with storage version 1, reduced to the messages the
+//! upgrade tests need. The storage struct keeps the version 1 name, root field
+//! order and types so that the current code can be upgraded onto it.
+
+pub use self::iabs_token_v1::{Error, IabsToken, IabsTokenRef};
+
+#[ink::contract]
+pub mod iabs_token_v1 {
+    use ink::prelude::string::String;
+    use ink::storage::Mapping;
+
+    #[ink(storage)]
+    pub struct IabsToken {
+        total_supply: Balance,
+        balances: Mapping<AccountId, Balance>,
+        allowances: Mapping<(AccountId, AccountId), Balance>,
+        owner: AccountId,
+        min_payment: Balance,
+        mint_amount: Balance,
+        name: Option<String>,
+        symbol: Option<String>,
+        decimals: u8,
+        redemption_rate: Balance,
+        paused: bool,
+        withdraw_when_paused: bool,
+        merkle_root: Option<[u8; 32]>,
+        presale_price: Balance,
+        presale_minted: Mapping<AccountId, u32>,
+        public_mint_open: bool,
+        storage_version: u32,
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum Error {
+        NotOwner,
+        InsufficientPayment,
+        UpgradeFailed,
+    }
+
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    impl Default for IabsToken {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl IabsToken {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self {
+                total_supply: 0,
+                balances: Mapping::default(),
+                allowances: Mapping::default(),
+                owner: Self::env().caller(),
+                min_payment: 10_000_000_000_000_000,
+                mint_amount: 1_000_000_000_000_000_000_000,
+                name: Some(String::from("IabsToken")),
+                symbol: Some(String::from("IABS")),
+                decimals: 18,
+                redemption_rate: 0,
+                paused: false,
+                withdraw_when_paused: true,
+                merkle_root: None,
+                presale_price: 10_000_000_000_000_000,
+                presale_minted: Mapping::default(),
+                public_mint_open: true,
+                storage_version: 1,
+            }
+        }
+
+        #[ink(message, payable)]
+        pub fn mint(&mut self) -> Result<()> {
+            let caller = self.env().caller();
+
+            if self.env().transferred_value() < self.min_payment {
+                return Err(Error::InsufficientPayment);
+            }
+
+            let balance = self.balances.get(caller).unwrap_or_default();
+            self.balances.insert(caller, &(balance + self.mint_amount));
+            self.total_supply += self.mint_amount;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn approve(&mut self, spender: AccountId, value: Balance) -> Result<()> {
+            self.allowances
+                .insert((self.env().caller(), spender), &value);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_merkle_root(&mut self, root: Option<[u8; 32]>) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+
+            self.merkle_root = root;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn upgrade(&mut self, code_hash: Hash) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+
+            self.env()
+                .set_code_hash(&code_hash)
+                .map_err(|_| Error::UpgradeFailed)
+        }
+
+        #[ink(message)]
+        pub fn balance_of(&self, owner: AccountId) -> Balance {
+            self.balances.get(owner).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn storage_version(&self) -> u32 {
+            self.storage_version
+        }
+    }
+}
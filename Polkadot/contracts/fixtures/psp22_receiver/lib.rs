@@ -0,0 +1,64 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+//! Minimal `PSP22Receiver` used by the `transfer_and_call` tests, accepting or
+//! rejecting every incoming transfer depending on its constructor argument.
+
+pub use self::psp22_receiver::{Psp22Receiver, Psp22ReceiverRef};
+
+#[ink::contract]
+pub mod psp22_receiver {
+    use iabs_token::{PSP22Receiver, PSP22ReceiverError};
+    use ink::prelude::{string::String, vec::Vec};
+
+    #[ink(storage)]
+    pub struct Psp22Receiver {
+        /// Whether incoming transfers are accepted
+        accept: bool,
+        /// Tokens accepted so far
+        received: Balance,
+        /// Data passed with the last accepted transfer
+        last_data: Vec<u8>,
+    }
+
+    impl Psp22Receiver {
+        #[ink(constructor)]
+        pub fn new(accept: bool) -> Self {
+            Self {
+                accept,
+                received: 0,
+                last_data: Vec::new(),
+            }
+        }
+
+        #[ink(message)]
+        pub fn received(&self) -> Balance {
+            self.received
+        }
+
+        #[ink(message)]
+        pub fn last_data(&self) -> Vec<u8> {
+            self.last_data.clone()
+        }
+    }
+
+    impl PSP22Receiver for Psp22Receiver {
+        #[ink(message)]
+        fn before_received(
+            &mut self,
+            _from: AccountId,
+            _operator: AccountId,
+            value: u128,
+            data: Vec<u8>,
+        ) -> Result<(), PSP22ReceiverError> {
+            if !self.accept {
+                return Err(PSP22ReceiverError::TransferRejected(String::from(
+                    "transfers not accepted",
+                )));
+            }
+
+            self.received = self.received.saturating_add(value);
+            self.last_data = data;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,439 @@
+#![cfg_attr(not(feature = "std"), no_std, no_main)]
+
+#[ink::contract]
+mod iabs_forwarder {
+    use iabs_token::TrustedForwarder;
+    use ink::env::call::{build_call, ExecutionInput, Selector};
+    use ink::env::hash::{Blake2x256, HashOutput};
+    use ink::env::CallFlags;
+    use ink::prelude::vec::Vec;
+    use ink::storage::{Lazy, Mapping};
+
+    /// A call signed by `from` and submitted by a relayer who pays the fees.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub struct ForwardRequest {
+        pub from: AccountId,
+        pub to: AccountId,
+        /// Native amount the relayer attaches to the call (e.g. the mint price)
+        pub value: Balance,
+        pub selector: [u8; 4],
+        /// SCALE encoded message arguments
+        pub input: Vec<u8>,
+        pub nonce: u64,
+        pub deadline: Timestamp,
+    }
+
+    #[ink(storage)]
+    pub struct IabsForwarder {
+        /// Chain identifier signed into requests (e.g. the parachain id)
+        chain_id: u32,
+        /// Next request nonce per signer
+        nonces: Mapping<AccountId, u64>,
+        /// Signer of the request being relayed, read back by the target contract
+        current_sender: Lazy<Option<AccountId>>,
+    }
+
+    #[ink(event)]
+    pub struct Forwarded {
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        nonce: u64,
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum Error {
+        ValueMismatch,
+        RequestExpired,
+        InvalidNonce,
+        InvalidSignature,
+        Overflow,
+        /// The target message returned an error, carrying its encoding
+        CallReverted(Vec<u8>),
+        CallFailed,
+    }
+
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// Domain tag prefixed to every request payload.
+    pub const FORWARD_DOMAIN: &[u8] = b"IABS::forward";
+
+    /// Message arguments passed through as already encoded bytes.
+    struct CallInput<'a>(&'a [u8]);
+
+    impl ink::scale::Encode for CallInput<'_> {
+        fn size_hint(&self) -> usize {
+            self.0.len()
+        }
+
+        fn encode_to<T: ink::scale::Output + ?Sized>(&self, dest: &mut T) {
+            dest.write(self.0);
+        }
+    }
+
+    /// Output of a relayed message returning `Result`, split into the variant
+    /// and its still encoded payload.
+    struct CallOutput(core::result::Result<Vec<u8>, Vec<u8>>);
+
+    impl ink::scale::Decode for CallOutput {
+        fn decode<I: ink::scale::Input>(
+            input: &mut I,
+        ) -> core::result::Result<Self, ink::scale::Error> {
+            let variant = input.read_byte()?;
+            let mut payload = ink::prelude::vec![0u8; input.remaining_len()?.unwrap_or_default()];
+            input.read(&mut payload)?;
+
+            match variant {
+                0 => Ok(Self(Ok(payload))),
+                1 => Ok(Self(Err(payload))),
+                _ => Err("relayed message must return a Result".into()),
+            }
+        }
+    }
+
+    impl IabsForwarder {
+        #[ink(constructor)]
+        pub fn new(chain_id: u32) -> Self {
+            Self {
+                chain_id,
+                nonces: Mapping::default(),
+                current_sender: Lazy::default(),
+            }
+        }
+
+        /// Relay `request` to its target on behalf of its signer.
+        ///
+        /// The relayer must attach exactly `request.value`. Targets recognise the
+        /// signer by calling back `msg_sender` while the request is relayed. Only
+        /// messages returning `Result` can be relayed; an `Err` reverts the whole
+        /// request, nonce included.
+        #[ink(message, payable)]
+        pub fn execute(&mut self, request: ForwardRequest, signature: [u8; 65]) -> Result<Vec<u8>> {
+            if self.env().transferred_value() != request.value {
+                return Err(Error::ValueMismatch);
+            }
+
+            if self.env().block_timestamp() > request.deadline {
+                return Err(Error::RequestExpired);
+            }
+
+            if request.nonce != self.nonces(request.from) {
+                return Err(Error::InvalidNonce);
+            }
+
+            if !self.verify(request.clone(), signature) {
+                return Err(Error::InvalidSignature);
+            }
+
+            let nonce = request.nonce.checked_add(1).ok_or(Error::Overflow)?;
+            self.nonces.insert(request.from, &nonce);
+
+            // the target calls back into `msg_sender` while handling the request
+            self.current_sender.set(&Some(request.from));
+            let result = build_call::<<Self as ink::env::ContractEnv>::Env>()
+                .call(request.to)
+                .transferred_value(request.value)
+                .call_flags(CallFlags::ALLOW_REENTRY)
+                .exec_input(
+                    ExecutionInput::new(Selector::new(request.selector))
+                        .push_arg(CallInput(&request.input)),
+                )
+                .returns::<CallOutput>()
+                .try_invoke();
+            self.current_sender.set(&None);
+
+            match result {
+                Ok(Ok(CallOutput(Ok(output)))) => {
+                    self.env().emit_event(Forwarded {
+                        from: request.from,
+                        to: request.to,
+                        nonce: request.nonce,
+                    });
+                    Ok(output)
+                }
+                Ok(Ok(CallOutput(Err(error)))) => Err(Error::CallReverted(error)),
+                _ => Err(Error::CallFailed),
+            }
+        }
+
+        /// Whether `signature` is a valid signature of `request` by `request.from`
+        #[ink(message)]
+        pub fn verify(&self, request: ForwardRequest, signature: [u8; 65]) -> bool {
+            let from = request.from;
+            let message_hash = self.request_hash(request);
+            let Ok(public_key) = self.env().ecdsa_recover(&signature, &message_hash) else {
+                return false;
+            };
+
+            let mut signer = <Blake2x256 as HashOutput>::Type::default();
+            ink::env::hash_bytes::<Blake2x256>(&public_key, &mut signer);
+            AccountId::from(signer) == from
+        }
+
+        /// Hash the signer of `request` must sign, bound to this forwarder and `chain_id`
+        #[ink(message)]
+        pub fn request_hash(&self, request: ForwardRequest) -> [u8; 32] {
+            let payload = (
+                FORWARD_DOMAIN,
+                self.chain_id,
+                self.env().account_id(),
+                request,
+            );
+            let mut output = <Blake2x256 as HashOutput>::Type::default();
+            ink::env::hash_encoded::<Blake2x256, _>(&payload, &mut output);
+            output
+        }
+
+        #[ink(message)]
+        pub fn nonces(&self, from: AccountId) -> u64 {
+            self.nonces.get(from).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn chain_id(&self) -> u32 {
+            self.chain_id
+        }
+    }
+
+    impl TrustedForwarder for IabsForwarder {
+        #[ink(message)]
+        fn msg_sender(&self) -> Option<AccountId> {
+            self.current_sender.get().flatten()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
+        fn execute_checks_request() {
+            use secp256k1::{Message, SecretKey, SECP256K1};
+
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut forwarder = IabsForwarder::new(2006);
+
+            let secret_key = SecretKey::from_slice(&[7u8; 32]).unwrap();
+            let public_key = secret_key.public_key(SECP256K1).serialize();
+            let mut from = [0u8; 32];
+            ink::env::hash_bytes::<Blake2x256>(&public_key, &mut from);
+            let from = AccountId::from(from);
+
+            let sign = |hash: [u8; 32]| {
+                let (recovery_id, rs) = SECP256K1
+                    .sign_ecdsa_recoverable(&Message::from_digest(hash), &secret_key)
+                    .serialize_compact();
+                let mut signature = [0u8; 65];
+                signature[..64].copy_from_slice(&rs);
+                signature[64] = recovery_id.to_i32() as u8;
+                signature
+            };
+
+            let request = ForwardRequest {
+                from,
+                to: accounts.django,
+                value: 100,
+                selector: ink::selector_bytes!("mint"),
+                input: Vec::new(),
+                nonce: 0,
+                deadline: 1_000,
+            };
+            let signature = sign(forwarder.request_hash(request.clone()));
+            assert!(forwarder.verify(request.clone(), signature));
+            assert_eq!(forwarder.msg_sender(), None);
+
+            // relayed by bob, who pays the fees
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(99);
+            assert_eq!(
+                forwarder.execute(request.clone(), signature),
+                Err(Error::ValueMismatch)
+            );
+
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(100);
+            let tampered = ForwardRequest {
+                to: accounts.eve,
+                ..request.clone()
+            };
+            assert_eq!(
+                forwarder.execute(tampered, signature),
+                Err(Error::InvalidSignature)
+            );
+
+            let replayed = ForwardRequest {
+                nonce: 1,
+                ..request.clone()
+            };
+            assert_eq!(
+                forwarder.execute(replayed, signature),
+                Err(Error::InvalidNonce)
+            );
+
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1_001);
+            assert_eq!(
+                forwarder.execute(request, signature),
+                Err(Error::RequestExpired)
+            );
+            assert_eq!(forwarder.nonces(from), 0);
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use iabs_token::{IabsToken, IabsTokenRef, PSP22};
+        use ink::scale::Encode;
+        use ink_e2e::ContractsBackend;
+        use secp256k1::{Message, SecretKey, SECP256K1};
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        /// Default `min_payment` of `IabsTokenRef::new` (0.01 SBY).
+        const MIN_PAYMENT: Balance = 10_000_000_000_000_000;
+
+        #[ink_e2e::test]
+        async fn relayed_mint_and_transfer_use_signer<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // given: a token trusting the forwarder
+            let mut constructor = IabsTokenRef::new();
+            let token = client
+                .instantiate("iabs_token", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate token failed");
+            let mut token_call_builder = token.call_builder::<IabsToken>();
+
+            let mut constructor = IabsForwarderRef::new(2006);
+            let forwarder = client
+                .instantiate("iabs_forwarder", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate forwarder failed");
+            let mut call_builder = forwarder.call_builder::<IabsForwarder>();
+
+            let set_trusted_forwarder =
+                token_call_builder.set_trusted_forwarder(Some(forwarder.account_id));
+            client
+                .call(&ink_e2e::alice(), &set_trusted_forwarder)
+                .submit()
+                .await
+                .expect("set_trusted_forwarder failed");
+
+            // the signer holds no native funds, bob relays and pays the fees
+            let secret_key = SecretKey::from_slice(&[7u8; 32]).unwrap();
+            let public_key = secret_key.public_key(SECP256K1).serialize();
+            let mut from = [0u8; 32];
+            ink::env::hash_bytes::<Blake2x256>(&public_key, &mut from);
+            let from = AccountId::from(from);
+            let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+            let charlie = ink_e2e::account_id(ink_e2e::AccountKeyring::Charlie);
+
+            let sign = |hash: [u8; 32]| {
+                let (recovery_id, rs) = SECP256K1
+                    .sign_ecdsa_recoverable(&Message::from_digest(hash), &secret_key)
+                    .serialize_compact();
+                let mut signature = [0u8; 65];
+                signature[..64].copy_from_slice(&rs);
+                signature[64] = recovery_id.to_i32() as u8;
+                signature
+            };
+
+            // when: a relayed mint
+            let request = ForwardRequest {
+                from,
+                to: token.account_id,
+                value: MIN_PAYMENT,
+                selector: ink::selector_bytes!("mint"),
+                input: Vec::new(),
+                nonce: 0,
+                deadline: Timestamp::MAX,
+            };
+            let request_hash = call_builder.request_hash(request.clone());
+            let hash = client
+                .call(&ink_e2e::bob(), &request_hash)
+                .dry_run()
+                .await?
+                .return_value();
+            let execute = call_builder.execute(request, sign(hash));
+            client
+                .call(&ink_e2e::bob(), &execute)
+                .value(MIN_PAYMENT)
+                .submit()
+                .await
+                .expect("relayed mint failed");
+
+            // then: the signer is credited, neither the relayer nor the forwarder
+            let mint_amount = token_call_builder.mint_amount();
+            let mint_amount = client
+                .call(&ink_e2e::bob(), &mint_amount)
+                .dry_run()
+                .await?
+                .return_value();
+            for (account, expected) in [(from, mint_amount), (bob, 0), (forwarder.account_id, 0)] {
+                let balance_of = token_call_builder.balance_of(account);
+                let balance = client
+                    .call(&ink_e2e::bob(), &balance_of)
+                    .dry_run()
+                    .await?
+                    .return_value();
+                assert_eq!(balance, expected);
+            }
+
+            // when: a relayed transfer
+            let request = ForwardRequest {
+                from,
+                to: token.account_id,
+                value: 0,
+                selector: ink::selector_bytes!("PSP22::transfer"),
+                input: (charlie, 100u128, Vec::<u8>::new()).encode(),
+                nonce: 1,
+                deadline: Timestamp::MAX,
+            };
+            let request_hash = call_builder.request_hash(request.clone());
+            let hash = client
+                .call(&ink_e2e::bob(), &request_hash)
+                .dry_run()
+                .await?
+                .return_value();
+            let signature = sign(hash);
+            let execute = call_builder.execute(request.clone(), signature);
+            client
+                .call(&ink_e2e::bob(), &execute)
+                .submit()
+                .await
+                .expect("relayed transfer failed");
+
+            // then: the tokens left the signer's balance
+            for (account, expected) in [(from, mint_amount - 100), (charlie, 100)] {
+                let balance_of = token_call_builder.balance_of(account);
+                let balance = client
+                    .call(&ink_e2e::bob(), &balance_of)
+                    .dry_run()
+                    .await?
+                    .return_value();
+                assert_eq!(balance, expected);
+            }
+
+            let nonces = call_builder.nonces(from);
+            let nonce = client
+                .call(&ink_e2e::bob(), &nonces)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(nonce, 2);
+
+            let replayed = client
+                .call(&ink_e2e::bob(), &execute)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(replayed, Err(Error::InvalidNonce));
+
+            Ok(())
+        }
+    }
+}
@@ -1,21 +1,303 @@
 #![cfg_attr(not(feature = "std"), no_std, no_main)]
 
+use ink::prelude::{string::String, vec::Vec};
+use ink::primitives::AccountId;
+
+#[derive(Debug, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub enum PSP22Error {
+    /// Any error not covered by the other variants, named after the token's own error
+    Custom(String),
+    InsufficientBalance,
+    InsufficientAllowance,
+    ZeroRecipientAddress,
+    ZeroSenderAddress,
+    SafeTransferCheckFailed(String),
+}
+
+/// PSP22 fungible token standard, giving wallets and other contracts the
+/// `PSP22::*` selectors they expect.
+#[ink::trait_definition]
+pub trait PSP22 {
+    /// Returns the total token supply.
+    #[ink(message)]
+    fn total_supply(&self) -> u128;
+
+    /// Returns the account balance for the specified `owner`.
+    #[ink(message)]
+    fn balance_of(&self, owner: AccountId) -> u128;
+
+    /// Returns the amount which `spender` is still allowed to withdraw from `owner`.
+    #[ink(message)]
+    fn allowance(&self, owner: AccountId, spender: AccountId) -> u128;
+
+    /// Transfers `value` amount of tokens from the caller's account to account `to`.
+    #[ink(message)]
+    fn transfer(&mut self, to: AccountId, value: u128, data: Vec<u8>) -> Result<(), PSP22Error>;
+
+    /// Transfers `value` tokens on the behalf of `from` to the account `to`.
+    ///
+    /// The caller must have been approved by `from` for at least `value` tokens.
+    #[ink(message)]
+    fn transfer_from(
+        &mut self,
+        from: AccountId,
+        to: AccountId,
+        value: u128,
+        data: Vec<u8>,
+    ) -> Result<(), PSP22Error>;
+
+    /// Allows `spender` to withdraw from the caller's account multiple times,
+    /// up to the `value` amount. Overwrites any existing allowance.
+    #[ink(message)]
+    fn approve(&mut self, spender: AccountId, value: u128) -> Result<(), PSP22Error>;
+
+    /// Atomically increases the allowance granted to `spender` by the caller.
+    #[ink(message)]
+    fn increase_allowance(
+        &mut self,
+        spender: AccountId,
+        delta_value: u128,
+    ) -> Result<(), PSP22Error>;
+
+    /// Atomically decreases the allowance granted to `spender` by the caller.
+    #[ink(message)]
+    fn decrease_allowance(
+        &mut self,
+        spender: AccountId,
+        delta_value: u128,
+    ) -> Result<(), PSP22Error>;
+}
+
+/// Optional PSP22 metadata extension.
+#[ink::trait_definition]
+pub trait PSP22Metadata {
+    /// Returns the token name.
+    #[ink(message)]
+    fn token_name(&self) -> Option<String>;
+
+    /// Returns the token symbol.
+    #[ink(message)]
+    fn token_symbol(&self) -> Option<String>;
+
+    /// Returns the token decimals.
+    #[ink(message)]
+    fn token_decimals(&self) -> u8;
+}
+
+#[derive(Debug, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub enum PSP22ReceiverError {
+    TransferRejected(String),
+}
+
+/// Hook implemented by contracts that want to react to incoming IABS sent
+/// with `transfer_and_call`.
+#[ink::trait_definition]
+pub trait PSP22Receiver {
+    /// Called by the token after `value` tokens were moved from `from` to this
+    /// contract on behalf of `operator`. Returning an error reverts the transfer.
+    #[ink(message)]
+    fn before_received(
+        &mut self,
+        from: AccountId,
+        operator: AccountId,
+        value: u128,
+        data: Vec<u8>,
+    ) -> Result<(), PSP22ReceiverError>;
+}
+
+/// Forwarder trusted by the token to relay signed requests for users who
+/// cannot pay transaction fees themselves.
+#[ink::trait_definition]
+pub trait TrustedForwarder {
+    /// Signer of the request currently being relayed, `None` outside a relay.
+    #[ink(message)]
+    fn msg_sender(&self) -> Option<AccountId>;
+}
+
+#[derive(Debug, PartialEq, Eq)]
+#[ink::scale_derive(Encode, Decode, TypeInfo)]
+pub enum FlashBorrowerError {
+    FlashloanRejected(String),
+}
+
+/// Hook implemented by contracts that borrow IABS through `flash_loan`.
+#[ink::trait_definition]
+pub trait FlashBorrower {
+    /// Called by the token after `amount` tokens were minted to this contract.
+    /// Before returning, the borrower must approve the token contract for
+    /// `amount + fee`, which is then burned (principal) and collected (fee).
+    #[ink(message)]
+    fn on_flashloan(
+        &mut self,
+        initiator: AccountId,
+        token: AccountId,
+        amount: u128,
+        fee: u128,
+        data: Vec<u8>,
+    ) -> Result<(), FlashBorrowerError>;
+}
+
+pub use self::iabs_token::{IabsToken, IabsTokenRef};
+
 #[ink::contract]
-mod iabs_token {
-    use ink::storage::Mapping;
+pub mod iabs_token {
+    use crate::{
+        FlashBorrower, FlashBorrowerError, PSP22Error, PSP22Metadata, PSP22Receiver,
+        PSP22ReceiverError, TrustedForwarder, PSP22,
+    };
+    use ink::codegen::TraitCallBuilder;
+    use ink::env::hash::{Blake2x256, HashOutput, Keccak256};
+    use ink::env::CallFlags;
+    use ink::env::ReturnErrorCode;
+    use ink::prelude::{format, string::String, vec::Vec};
+    use ink::storage::{Lazy, Mapping};
 
+    /// The root cell keeps the version 1 layout so deployed instances can be
+    /// upgraded in place. Version 2 moved the supply, owner, mint economics, name,
+    /// symbol and presale root out of it into `Lazy` cells; `migrate` empties
+    /// their old slots.
     #[ink(storage)]
     pub struct IabsToken {
         /// Total token supply.
-        total_supply: Balance,
+        total_supply: Lazy<Balance>,
+        /// Version 1 slot of the total supply, 0 once migrated
+        legacy_total_supply: Balance,
         /// Mapping from owner to number of owned tokens.
         balances: Mapping<AccountId, Balance>,
+        /// Mapping of the token amount which an account is allowed to withdraw
+        /// from another account.
+        allowances: Mapping<(AccountId, AccountId), Balance>,
         /// The contract owner (who can withdraw funds).
-        owner: AccountId,
+        owner: Lazy<AccountId>,
+        /// Version 1 slot of the owner, zeroed once migrated
+        legacy_owner: AccountId,
         /// Minimum payment required to mint tokens (0.01 SBY)
-        min_payment: Balance,
+        min_payment: Lazy<Balance>,
+        /// Version 1 slot of the minimum payment, 0 once migrated
+        legacy_min_payment: Balance,
         /// Amount of tokens minted per payment (1000 IABS)
-        mint_amount: Balance,
+        mint_amount: Lazy<Balance>,
+        /// Version 1 slot of the mint amount, 0 once migrated
+        legacy_mint_amount: Balance,
+        /// Token name (PSP22Metadata).
+        name: Lazy<Option<String>>,
+        /// Version 1 slot of the token name, `None` once migrated
+        legacy_name: Option<String>,
+        /// Token symbol (PSP22Metadata).
+        symbol: Lazy<Option<String>>,
+        /// Version 1 slot of the token symbol, `None` once migrated
+        legacy_symbol: Option<String>,
+        /// Number of decimals used for display purposes (PSP22Metadata).
+        decimals: u8,
+        /// Native amount paid back per whole token on redeem (0 disables redeem)
+        redemption_rate: Balance,
+        /// Circuit breaker for mint, transfers and withdraw
+        paused: bool,
+        /// Whether the owner may still withdraw while the contract is paused
+        withdraw_when_paused: bool,
+        /// Merkle root of the presale allowlist (presale closed when `None`)
+        merkle_root: Lazy<Option<[u8; 32]>>,
+        /// Version 1 slot of the presale Merkle root, `None` once migrated
+        legacy_merkle_root: Option<[u8; 32]>,
+        /// Payment required per presale mint
+        presale_price: Balance,
+        /// Number of presale mints already used per account
+        presale_minted: Mapping<AccountId, u32>,
+        /// Whether the plain `mint` is open to everyone
+        public_mint_open: bool,
+        /// Layout version of this storage, bumped by `migrate` after an `upgrade`
+        storage_version: u32,
+        /// Chain identifier signed into permits (e.g. the parachain id)
+        chain_id: Lazy<u32>,
+        /// Next permit nonce per owner
+        nonces: Mapping<AccountId, u64>,
+        /// Optional (start, end) timestamps bounding the public `mint`
+        sale_window: Lazy<SaleWindow>,
+        /// Revenue payees with their share in basis points (summing to 10_000)
+        payees: Lazy<Vec<(AccountId, u16)>>,
+        /// Native amount already released per payee
+        released: Mapping<AccountId, Balance>,
+        /// Native amount released to all payees
+        total_released: Lazy<Balance>,
+        /// Flash loan fee in basis points of the borrowed amount
+        flash_fee_bps: Lazy<u16>,
+        /// Balance history per account as (block, balance at the end of that block)
+        balance_checkpoints: Mapping<(AccountId, u32), Checkpoint>,
+        /// Number of balance checkpoints per account
+        balance_checkpoint_count: Mapping<AccountId, u32>,
+        /// Total supply history as (block, supply at the end of that block)
+        supply_checkpoints: Mapping<u32, Checkpoint>,
+        /// Number of total supply checkpoints
+        supply_checkpoint_count: Lazy<u32>,
+        /// Governance proposals by id
+        proposals: Mapping<u32, Proposal>,
+        /// Number of proposals created so far (next proposal id)
+        proposal_count: Lazy<u32>,
+        /// Recorded votes per (proposal id, voter), `true` meaning in favour
+        proposal_votes: Mapping<(u32, AccountId), bool>,
+        /// Number of blocks a proposal stays open for voting
+        voting_period: Lazy<BlockNumber>,
+        /// Share of the snapshot supply that has to vote, in basis points
+        quorum_bps: Lazy<u16>,
+        /// Share of the cast votes that has to be in favour, in basis points
+        approval_threshold_bps: Lazy<u16>,
+        /// Minimum time between scheduling and applying an owner change (0 disables the timelock)
+        timelock_delay: Lazy<Timestamp>,
+        /// Owner changes waiting for their eta, by id
+        scheduled_changes: Mapping<u32, ScheduledChange>,
+        /// Number of changes scheduled so far (next change id)
+        scheduled_change_count: Lazy<u32>,
+        /// Ids of the changes neither executed nor cancelled yet
+        pending_change_ids: Lazy<Vec<u32>>,
+        /// Forwarder allowed to act on behalf of the signer of a relayed request
+        trusted_forwarder: Lazy<Option<AccountId>>,
+        /// Whether the public sale goes through `commit`/`reveal` instead of `mint`
+        commit_reveal_enabled: Lazy<bool>,
+        /// Blocks to wait after `commit` before `reveal` is accepted
+        reveal_delay: Lazy<BlockNumber>,
+        /// Blocks after the delay during which `reveal` is accepted
+        reveal_window: Lazy<BlockNumber>,
+        /// Pending mint commitment per account
+        commitments: Mapping<AccountId, Commitment>,
+        /// Payments held for pending commitments, kept out of withdraw and payouts
+        committed_payments: Lazy<Balance>,
+        /// Native amount lendable per collateral value, in basis points (0 disables lending)
+        loan_to_value_bps: Lazy<u16>,
+        /// Debt share of the collateral value above which a loan can be liquidated
+        liquidation_threshold_bps: Lazy<u16>,
+        /// Interest added to a debt per block, in parts per billion
+        interest_rate_ppb: Lazy<u32>,
+        /// Collateral and debt per borrower
+        loans: Mapping<AccountId, Loan>,
+        /// Number of accounts with collateral or debt
+        open_loans: Lazy<u32>,
+        /// End of the sunset claim period, `None` until the owner starts the sunset
+        sunset_claim_end: Lazy<Option<Timestamp>>,
+        /// Set while the borrower of a flash loan is being called back
+        flash_loan_active: Lazy<bool>,
+    }
+
+    /// Event emitted when a token transfer occurs.
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        value: Balance,
+    }
+
+    /// Event emitted when an approval occurs that `spender` is allowed to withdraw
+    /// up to the amount of `value` tokens from `owner`.
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        spender: AccountId,
+        value: Balance,
     }
 
     #[ink(event)]
@@ -27,183 +309,5295 @@ mod iabs_token {
     }
 
     #[ink(event)]
-    pub struct Withdraw {
+    pub struct MerkleRootUpdated {
+        root: Option<[u8; 32]>,
+    }
+
+    #[ink(event)]
+    pub struct PresalePriceUpdated {
         #[ink(topic)]
-        owner: AccountId,
-        amount: Balance,
+        old: Balance,
+        #[ink(topic)]
+        new: Balance,
     }
 
-    #[derive(Debug, PartialEq, Eq)]
-    #[ink::scale_derive(Encode, Decode, TypeInfo)]
-    pub enum Error {
-        InsufficientBalance,
-        NotOwner,
-        InsufficientPayment,
-        NoFundsToWithdraw,
-        TransferFailed,
+    #[ink(event)]
+    pub struct PublicMintUpdated {
+        open: bool,
     }
 
-    pub type Result<T> = core::result::Result<T, Error>;
+    #[ink(event)]
+    pub struct Burn {
+        #[ink(topic)]
+        from: AccountId,
+        amount: Balance,
+    }
 
-    impl IabsToken {
-        #[ink(constructor)]
-        pub fn new() -> Self {
-            let caller = Self::env().caller();
+    #[ink(event)]
+    pub struct Redeem {
+        #[ink(topic)]
+        from: AccountId,
+        amount: Balance,
+        payout: Balance,
+    }
 
-            Self {
-                total_supply: 0,
-                balances: Mapping::default(),
-                owner: caller,
-                min_payment: 10_000_000_000_000_000, // 0.01 SBY
-                mint_amount: 1_000_000_000_000_000_000_000, // 1000 tokens with 18 decimals
-            }
-        }
+    #[ink(event)]
+    pub struct Withdraw {
+        #[ink(topic)]
+        owner: AccountId,
+        amount: Balance,
+    }
 
-        #[ink(message, payable)]
-        pub fn mint(&mut self) -> Result<()> {
-            let caller = self.env().caller();
-            let payment = self.env().transferred_value();
+    #[ink(event)]
+    pub struct MinPaymentUpdated {
+        #[ink(topic)]
+        old: Balance,
+        #[ink(topic)]
+        new: Balance,
+    }
 
-            if payment < self.min_payment {
-                return Err(Error::InsufficientPayment);
-            }
+    #[ink(event)]
+    pub struct MintAmountUpdated {
+        #[ink(topic)]
+        old: Balance,
+        #[ink(topic)]
+        new: Balance,
+    }
 
-            let current_balance = self.balance_of(caller);
-            let new_balance = current_balance.saturating_add(self.mint_amount);
+    #[ink(event)]
+    pub struct RedemptionRateUpdated {
+        #[ink(topic)]
+        old: Balance,
+        #[ink(topic)]
+        new: Balance,
+    }
 
-            self.balances.insert(caller, &new_balance);
-            self.total_supply = self.total_supply.saturating_add(self.mint_amount);
+    #[ink(event)]
+    pub struct WithdrawWhenPausedUpdated {
+        allowed: bool,
+    }
 
-            self.env().emit_event(Mint {
-                to: caller,
-                amount: self.mint_amount,
-                payment,
-            });
+    #[ink(event)]
+    pub struct OwnershipTransferred {
+        #[ink(topic)]
+        previous: Option<AccountId>,
+        #[ink(topic)]
+        new: AccountId,
+    }
 
-            Ok(())
-        }
+    #[ink(event)]
+    pub struct TrustedForwarderUpdated {
+        #[ink(topic)]
+        old: Option<AccountId>,
+        #[ink(topic)]
+        new: Option<AccountId>,
+    }
 
-        #[ink(message)]
-        pub fn withdraw(&mut self) -> Result<()> {
-            let caller = self.env().caller();
+    #[ink(event)]
+    pub struct Committed {
+        #[ink(topic)]
+        account: AccountId,
+        hash: [u8; 32],
+        payment: Balance,
+    }
 
-            if caller != self.owner {
-                return Err(Error::NotOwner);
-            }
+    #[ink(event)]
+    pub struct CommitmentRefunded {
+        #[ink(topic)]
+        account: AccountId,
+        payment: Balance,
+    }
 
-            let balance = self.env().balance();
-            let minimum_balance = self.env().minimum_balance();
+    #[ink(event)]
+    pub struct CommitRevealUpdated {
+        enabled: bool,
+        reveal_delay: BlockNumber,
+        reveal_window: BlockNumber,
+    }
 
-            if balance <= minimum_balance {
-                return Err(Error::NoFundsToWithdraw);
-            }
+    #[ink(event)]
+    pub struct CollateralDeposited {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
 
-            let withdraw_amount = balance.saturating_sub(minimum_balance);
+    #[ink(event)]
+    pub struct CollateralWithdrawn {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
 
-            if withdraw_amount == 0 {
-                return Err(Error::NoFundsToWithdraw);
-            }
+    #[ink(event)]
+    pub struct Borrowed {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
 
-            // Use match instead of is_err() for better error handling
-            match self.env().transfer(self.owner, withdraw_amount) {
-                Ok(_) => {
-                    self.env().emit_event(Withdraw {
-                        owner: self.owner,
-                        amount: withdraw_amount,
-                    });
-                    Ok(())
-                }
-                Err(_) => Err(Error::TransferFailed),
-            }
-        }
+    #[ink(event)]
+    pub struct Repaid {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
 
-        #[ink(message)]
-        pub fn balance_of(&self, owner: AccountId) -> Balance {
-            self.balances.get(owner).unwrap_or_default()
-        }
+    #[ink(event)]
+    pub struct Liquidated {
+        #[ink(topic)]
+        borrower: AccountId,
+        debt: Balance,
+        seized: Balance,
+    }
 
-        #[ink(message)]
-        pub fn total_supply(&self) -> Balance {
-            self.total_supply
-        }
+    #[ink(event)]
+    pub struct LendingConfigUpdated {
+        loan_to_value_bps: u16,
+        liquidation_threshold_bps: u16,
+        interest_rate_ppb: u32,
+    }
 
-        #[ink(message)]
-        pub fn owner(&self) -> AccountId {
-            self.owner
-        }
+    #[ink(event)]
+    pub struct SunsetStarted {
+        claim_end: Timestamp,
+    }
 
-        #[ink(message)]
-        pub fn min_payment(&self) -> Balance {
-            self.min_payment
-        }
+    #[ink(event)]
+    pub struct SunsetClaimed {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+        payout: Balance,
+    }
 
-        #[ink(message)]
-        pub fn mint_amount(&self) -> Balance {
-            self.mint_amount
-        }
+    #[ink(event)]
+    pub struct ContractTerminated {
+        #[ink(topic)]
+        beneficiary: AccountId,
+        residue: Balance,
+    }
 
-        #[ink(message)]
-        pub fn contract_balance(&self) -> Balance {
-            self.env().balance()
-        }
+    #[ink(event)]
+    pub struct ProposalCreated {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        proposer: AccountId,
+        action: ProposalAction,
+        end_block: BlockNumber,
+    }
 
-        /// Allow owner to update minimum payment
-        #[ink(message)]
-        pub fn set_min_payment(&mut self, new_min_payment: Balance) -> Result<()> {
-            let caller = self.env().caller();
+    #[ink(event)]
+    pub struct VoteCast {
+        #[ink(topic)]
+        id: u32,
+        #[ink(topic)]
+        voter: AccountId,
+        support: bool,
+        weight: Balance,
+    }
 
-            if caller != self.owner {
-                return Err(Error::NotOwner);
-            }
+    #[ink(event)]
+    pub struct ProposalExecuted {
+        #[ink(topic)]
+        id: u32,
+        action: ProposalAction,
+    }
 
-            self.min_payment = new_min_payment;
-            Ok(())
-        }
+    #[ink(event)]
+    pub struct GovernanceConfigUpdated {
+        voting_period: BlockNumber,
+        quorum_bps: u16,
+        approval_threshold_bps: u16,
+    }
 
-        /// Allow owner to update mint amount
-        #[ink(message)]
-        pub fn set_mint_amount(&mut self, new_mint_amount: Balance) -> Result<()> {
-            let caller = self.env().caller();
+    #[ink(event)]
+    pub struct ChangeScheduled {
+        #[ink(topic)]
+        id: u32,
+        action: ProposalAction,
+        eta: Timestamp,
+    }
 
-            if caller != self.owner {
-                return Err(Error::NotOwner);
-            }
+    #[ink(event)]
+    pub struct ChangeExecuted {
+        #[ink(topic)]
+        id: u32,
+        action: ProposalAction,
+    }
 
-            self.mint_amount = new_mint_amount;
-            Ok(())
-        }
+    #[ink(event)]
+    pub struct ChangeCancelled {
+        #[ink(topic)]
+        id: u32,
+    }
 
-        /// Transfer ownership to a new owner
-        #[ink(message)]
-        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<()> {
-            let caller = self.env().caller();
+    #[ink(event)]
+    pub struct TimelockDelayUpdated {
+        #[ink(topic)]
+        old: Timestamp,
+        #[ink(topic)]
+        new: Timestamp,
+    }
 
-            if caller != self.owner {
-                return Err(Error::NotOwner);
-            }
+    #[ink(event)]
+    pub struct FlashLoan {
+        #[ink(topic)]
+        receiver: AccountId,
+        #[ink(topic)]
+        initiator: AccountId,
+        amount: Balance,
+        fee: Balance,
+    }
 
-            self.owner = new_owner;
-            Ok(())
-        }
+    #[ink(event)]
+    pub struct FlashFeeUpdated {
+        #[ink(topic)]
+        old: u16,
+        #[ink(topic)]
+        new: u16,
     }
 
-    #[cfg(test)]
-    mod tests {
-        use super::*;
+    #[ink(event)]
+    pub struct PayeesUpdated {
+        payees: Vec<(AccountId, u16)>,
+    }
 
-        #[ink::test]
+    #[ink(event)]
+    pub struct PaymentReleased {
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct SaleWindowUpdated {
+        start: Option<Timestamp>,
+        end: Option<Timestamp>,
+    }
+
+    #[ink(event)]
+    pub struct ChainIdUpdated {
+        #[ink(topic)]
+        old: u32,
+        #[ink(topic)]
+        new: u32,
+    }
+
+    #[ink(event)]
+    pub struct Upgraded {
+        #[ink(topic)]
+        code_hash: Hash,
+    }
+
+    #[ink(event)]
+    pub struct Migrated {
+        from: u32,
+        to: u32,
+    }
+
+    #[ink(event)]
+    pub struct Paused {
+        #[ink(topic)]
+        by: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct Unpaused {
+        #[ink(topic)]
+        by: AccountId,
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum Error {
+        InsufficientBalance,
+        InsufficientAllowance,
+        NotOwner,
+        InsufficientPayment,
+        NoFundsToWithdraw,
+        TransferFailed,
+        InsufficientReserve,
+        RedemptionDisabled,
+        ContractPaused,
+        PublicMintClosed,
+        PresaleNotActive,
+        InvalidProof,
+        PresaleAllowanceExceeded,
+        UpgradeFailed,
+        NothingToMigrate,
+        SafeTransferCheckFailed(String),
+        Overflow,
+        PermitExpired,
+        InvalidSignature,
+        SaleNotStarted,
+        SaleEnded,
+        InvalidSaleWindow,
+        InvalidShares,
+        PayeesLocked,
+        PaymentSplitActive,
+        NotPayee,
+        NothingToRelease,
+        InvalidFee,
+        FlashLoanRejected(String),
+        NoVotingPower,
+        ProposalNotFound,
+        VotingClosed,
+        VotingActive,
+        AlreadyVoted,
+        ProposalAlreadyExecuted,
+        QuorumNotReached,
+        ProposalRejected,
+        InvalidGovernanceConfig,
+        TimelockActive,
+        InvalidTimelockDelay,
+        EtaTooEarly,
+        ChangeNotFound,
+        ChangeNotReady,
+        CommitRevealRequired,
+        CommitRevealDisabled,
+        CallerNotOrigin,
+        CommitmentExists,
+        NoCommitment,
+        RevealTooEarly,
+        CommitmentExpired,
+        CommitmentNotExpired,
+        InvalidReveal,
+        InvalidCommitRevealConfig,
+        LendingDisabled,
+        InvalidLendingConfig,
+        NoLoan,
+        Undercollateralised,
+        NotLiquidatable,
+        NothingToRepay,
+        SunsetActive,
+        SunsetNotStarted,
+        ClaimPeriodActive,
+        ClaimPeriodEnded,
+        /// The trusted forwarder could not name the signer of the relayed call
+        UnknownSender,
+        /// Only `approve` is accepted while a flash loan waits for its repayment
+        FlashLoanActive,
+        /// The sunset cannot start while collateral is locked or debt is owed
+        LoansOpen,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    pub enum SaleStatus {
+        NotStarted,
+        Active,
+        Ended,
+    }
+
+    /// Parameter change carried out by a passing governance proposal.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub enum ProposalAction {
+        SetMinPayment(Balance),
+        SetMintAmount(Balance),
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub struct Proposal {
+        pub proposer: AccountId,
+        pub action: ProposalAction,
+        /// Votes are weighted by balances at the end of this block
+        pub snapshot_block: BlockNumber,
+        /// Last block in which votes are accepted
+        pub end_block: BlockNumber,
+        pub votes_for: Balance,
+        pub votes_against: Balance,
+        pub executed: bool,
+    }
+
+    /// Owner change queued behind the timelock.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub struct ScheduledChange {
+        pub id: u32,
+        pub action: ProposalAction,
+        /// Earliest timestamp at which the change can be executed
+        pub eta: Timestamp,
+    }
+
+    /// Payment committed to a future `reveal`.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub struct Commitment {
+        /// `commitment_hash(account, secret)`
+        pub hash: [u8; 32],
+        pub payment: Balance,
+        /// First block in which `reveal` is accepted
+        pub reveal_start: BlockNumber,
+        /// Last block in which `reveal` is accepted, refundable afterwards
+        pub reveal_end: BlockNumber,
+    }
+
+    /// Collateral locked by a borrower and the native amount owed.
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    #[ink::scale_derive(Encode, Decode, TypeInfo)]
+    #[cfg_attr(feature = "std", derive(ink::storage::traits::StorageLayout))]
+    pub struct Loan {
+        pub collateral: Balance,
+        /// Debt including the interest accrued up to `last_accrual`
+        pub debt: Balance,
+        pub last_accrual: BlockNumber,
+    }
+
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// Optional (start, end) bounds of the public sale.
+    pub type SaleWindow = (Option<Timestamp>, Option<Timestamp>);
+
+    /// Block number and the value held at the end of that block.
+    pub type Checkpoint = (BlockNumber, Balance);
+
+    /// Storage layout version produced by this code.
+    ///
+    /// Version 2 moved the total supply, owner, `min_payment`, `mint_amount`, token
+    /// name, symbol and presale Merkle root into `Lazy` cells, keeping their
+    /// version 1 root slots so older instances still decode.
+    pub const STORAGE_VERSION: u32 = 2;
+
+    /// Domain tag prefixed to every permit payload.
+    pub const PERMIT_DOMAIN: &[u8] = b"IABS::permit";
+
+    /// Default voting period, about one day of 12 second blocks.
+    pub const DEFAULT_VOTING_PERIOD: BlockNumber = 7_200;
+
+    /// Default quorum, 4% of the snapshot supply.
+    pub const DEFAULT_QUORUM_BPS: u16 = 400;
+
+    /// Default approval threshold, a strict majority of the cast votes.
+    pub const DEFAULT_APPROVAL_THRESHOLD_BPS: u16 = 5_000;
+
+    /// Denominator of `interest_rate_ppb`.
+    pub const INTEREST_SCALE: Balance = 1_000_000_000;
+
+    impl Default for IabsToken {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl IabsToken {
+        /// Default IABS preset: 0.01 SBY for 1000 tokens with 18 decimals
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self::new_with_config(
+                Some(String::from("IabsToken")),
+                Some(String::from("IABS")),
+                18,
+                10_000_000_000_000_000,        // 0.01 SBY
+                1_000_000_000_000_000_000_000, // 1000 tokens with 18 decimals
+            )
+        }
+
+        /// Deploy a token with custom metadata and mint economics
+        #[ink(constructor)]
+        pub fn new_with_config(
+            name: Option<String>,
+            symbol: Option<String>,
+            decimals: u8,
+            min_payment: Balance,
+            mint_amount: Balance,
+        ) -> Self {
+            let caller = Self::env().caller();
+
+            Self::env().emit_event(OwnershipTransferred {
+                previous: None,
+                new: caller,
+            });
+
+            let mut instance = Self {
+                total_supply: Lazy::default(),
+                legacy_total_supply: 0,
+                balances: Mapping::default(),
+                allowances: Mapping::default(),
+                owner: Lazy::default(),
+                legacy_owner: AccountId::from([0x00; 32]),
+                min_payment: Lazy::default(),
+                legacy_min_payment: 0,
+                mint_amount: Lazy::default(),
+                legacy_mint_amount: 0,
+                name: Lazy::default(),
+                legacy_name: None,
+                symbol: Lazy::default(),
+                legacy_symbol: None,
+                decimals,
+                redemption_rate: 0,
+                paused: false,
+                withdraw_when_paused: true,
+                merkle_root: Lazy::default(),
+                legacy_merkle_root: None,
+                presale_price: min_payment,
+                presale_minted: Mapping::default(),
+                public_mint_open: true,
+                storage_version: STORAGE_VERSION,
+                chain_id: Lazy::default(),
+                nonces: Mapping::default(),
+                sale_window: Lazy::default(),
+                payees: Lazy::default(),
+                released: Mapping::default(),
+                total_released: Lazy::default(),
+                flash_fee_bps: Lazy::default(),
+                balance_checkpoints: Mapping::default(),
+                balance_checkpoint_count: Mapping::default(),
+                supply_checkpoints: Mapping::default(),
+                supply_checkpoint_count: Lazy::default(),
+                proposals: Mapping::default(),
+                proposal_count: Lazy::default(),
+                proposal_votes: Mapping::default(),
+                voting_period: Lazy::default(),
+                quorum_bps: Lazy::default(),
+                approval_threshold_bps: Lazy::default(),
+                timelock_delay: Lazy::default(),
+                scheduled_changes: Mapping::default(),
+                scheduled_change_count: Lazy::default(),
+                pending_change_ids: Lazy::default(),
+                trusted_forwarder: Lazy::default(),
+                commit_reveal_enabled: Lazy::default(),
+                reveal_delay: Lazy::default(),
+                reveal_window: Lazy::default(),
+                commitments: Mapping::default(),
+                committed_payments: Lazy::default(),
+                loan_to_value_bps: Lazy::default(),
+                liquidation_threshold_bps: Lazy::default(),
+                interest_rate_ppb: Lazy::default(),
+                loans: Mapping::default(),
+                open_loans: Lazy::default(),
+                sunset_claim_end: Lazy::default(),
+                flash_loan_active: Lazy::default(),
+            };
+
+            instance.total_supply.set(&0);
+            instance.owner.set(&caller);
+            instance.min_payment.set(&min_payment);
+            instance.mint_amount.set(&mint_amount);
+            instance.name.set(&name);
+            instance.symbol.set(&symbol);
+            instance.merkle_root.set(&None);
+            instance.voting_period.set(&DEFAULT_VOTING_PERIOD);
+            instance.quorum_bps.set(&DEFAULT_QUORUM_BPS);
+            instance
+                .approval_threshold_bps
+                .set(&DEFAULT_APPROVAL_THRESHOLD_BPS);
+            instance
+        }
+
+        #[ink(message, payable)]
+        pub fn mint(&mut self) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.sender()?;
+            let payment = self.env().transferred_value();
+
+            self.ensure_public_sale()?;
+
+            if self.commit_reveal_enabled() {
+                return Err(Error::CommitRevealRequired);
+            }
+
+            if payment < self.min_payment() {
+                return Err(Error::InsufficientPayment);
+            }
+
+            self.mint_to(caller, payment)
+        }
+
+        /// Presale mint for accounts in the allowlist Merkle tree.
+        ///
+        /// Each leaf is `keccak256(scale_encode((account, max_allowance)))` and
+        /// pairs are hashed in sorted order. `max_allowance` is the number of
+        /// presale mints the account may perform at `presale_price`.
+        #[ink(message, payable)]
+        pub fn mint_allowlisted(&mut self, proof: Vec<[u8; 32]>, max_allowance: u32) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.sender()?;
+            let payment = self.env().transferred_value();
+
+            self.ensure_not_paused()?;
+            self.ensure_not_sunset()?;
+
+            let root = self.merkle_root().ok_or(Error::PresaleNotActive)?;
+
+            if payment < self.presale_price() {
+                return Err(Error::InsufficientPayment);
+            }
+
+            if !Self::verify_proof(&proof, root, Self::allowlist_leaf(caller, max_allowance)) {
+                return Err(Error::InvalidProof);
+            }
+
+            let used = self.presale_minted(caller);
+
+            if used >= max_allowance {
+                return Err(Error::PresaleAllowanceExceeded);
+            }
+
+            let used = used.checked_add(1).ok_or(Error::Overflow)?;
+            self.presale_minted.insert(caller, &used);
+            self.mint_to(caller, payment)
+        }
+
+        /// First step of a commit-reveal mint: pay now and lock in
+        /// `commitment_hash(caller, secret)` for a later `reveal`.
+        ///
+        /// Only accounts calling directly (not through a contract) can commit.
+        #[ink(message, payable)]
+        pub fn commit(&mut self, hash: [u8; 32]) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+            let payment = self.env().transferred_value();
+
+            self.ensure_public_sale()?;
+
+            if !self.commit_reveal_enabled() {
+                return Err(Error::CommitRevealDisabled);
+            }
+
+            if !self.env().caller_is_origin() {
+                return Err(Error::CallerNotOrigin);
+            }
+
+            if self.commitments.contains(caller) {
+                return Err(Error::CommitmentExists);
+            }
+
+            if payment < self.min_payment() {
+                return Err(Error::InsufficientPayment);
+            }
+
+            let committed_payments = self
+                .committed_payments()
+                .checked_add(payment)
+                .ok_or(Error::Overflow)?;
+
+            // fixed now so that a later `set_commit_reveal` leaves pending commitments alone
+            let reveal_start = self
+                .env()
+                .block_number()
+                .checked_add(self.reveal_delay())
+                .ok_or(Error::Overflow)?;
+            let reveal_end = reveal_start
+                .checked_add(self.reveal_window())
+                .ok_or(Error::Overflow)?;
+
+            self.commitments.insert(
+                caller,
+                &Commitment {
+                    hash,
+                    payment,
+                    reveal_start,
+                    reveal_end,
+                },
+            );
+            self.committed_payments.set(&committed_payments);
+
+            self.env().emit_event(Committed {
+                account: caller,
+                hash,
+                payment,
+            });
+            Ok(())
+        }
+
+        /// Second step of a commit-reveal mint: between `reveal_delay` and
+        /// `reveal_delay + reveal_window` blocks after `commit`, as configured when
+        /// committing, disclose the secret to receive `mint_amount` tokens.
+        #[ink(message)]
+        pub fn reveal(&mut self, secret: [u8; 32]) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            self.ensure_not_paused()?;
+            self.ensure_not_sunset()?;
+
+            let commitment = self.commitments.get(caller).ok_or(Error::NoCommitment)?;
+
+            if !self.env().caller_is_origin() {
+                return Err(Error::CallerNotOrigin);
+            }
+
+            let current_block = self.env().block_number();
+
+            if current_block < commitment.reveal_start {
+                return Err(Error::RevealTooEarly);
+            }
+            if current_block > commitment.reveal_end {
+                return Err(Error::CommitmentExpired);
+            }
+            if self.commitment_hash(caller, secret) != commitment.hash {
+                return Err(Error::InvalidReveal);
+            }
+
+            self.commitments.remove(caller);
+            self.committed_payments
+                .set(&self.committed_payments().saturating_sub(commitment.payment));
+            self.mint_to(caller, commitment.payment)
+        }
+
+        /// Pay back a commitment that was not revealed in time
+        #[ink(message)]
+        pub fn refund_commitment(&mut self) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            let commitment = self.commitments.get(caller).ok_or(Error::NoCommitment)?;
+
+            // commitments can no longer be revealed once the sunset started
+            if self.env().block_number() <= commitment.reveal_end
+                && self.sunset_claim_end().is_none()
+            {
+                return Err(Error::CommitmentNotExpired);
+            }
+
+            self.commitments.remove(caller);
+            self.committed_payments
+                .set(&self.committed_payments().saturating_sub(commitment.payment));
+
+            if self.env().transfer(caller, commitment.payment).is_err() {
+                return Err(Error::TransferFailed);
+            }
+
+            self.env().emit_event(CommitmentRefunded {
+                account: caller,
+                payment: commitment.payment,
+            });
+            Ok(())
+        }
+
+        /// Hash to `commit` for revealing `secret` from `account`
+        #[ink(message)]
+        pub fn commitment_hash(&self, account: AccountId, secret: [u8; 32]) -> [u8; 32] {
+            let mut output = <Blake2x256 as HashOutput>::Type::default();
+            ink::env::hash_encoded::<Blake2x256, _>(&(account, secret), &mut output);
+            output
+        }
+
+        #[ink(message)]
+        pub fn commitment(&self, account: AccountId) -> Option<Commitment> {
+            self.commitments.get(account)
+        }
+
+        #[ink(message)]
+        pub fn committed_payments(&self) -> Balance {
+            self.committed_payments.get().unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn commit_reveal_enabled(&self) -> bool {
+            self.commit_reveal_enabled.get().unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn reveal_delay(&self) -> BlockNumber {
+            self.reveal_delay.get().unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn reveal_window(&self) -> BlockNumber {
+            self.reveal_window.get().unwrap_or_default()
+        }
+
+        /// Allow owner to switch the public sale to commit-reveal and tune its timing.
+        /// Pending commitments keep the window they were made with.
+        #[ink(message)]
+        pub fn set_commit_reveal(
+            &mut self,
+            enabled: bool,
+            reveal_delay: BlockNumber,
+            reveal_window: BlockNumber,
+        ) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            if enabled && (reveal_delay == 0 || reveal_window == 0) {
+                return Err(Error::InvalidCommitRevealConfig);
+            }
+
+            self.commit_reveal_enabled.set(&enabled);
+            self.reveal_delay.set(&reveal_delay);
+            self.reveal_window.set(&reveal_window);
+            self.env().emit_event(CommitRevealUpdated {
+                enabled,
+                reveal_delay,
+                reveal_window,
+            });
+            Ok(())
+        }
+
+        /// Lock `amount` of the caller's tokens as collateral for borrowing
+        #[ink(message)]
+        pub fn deposit_collateral(&mut self, amount: Balance) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            self.ensure_not_sunset()?;
+
+            if self.loan_to_value_bps() == 0 {
+                return Err(Error::LendingDisabled);
+            }
+
+            let mut loan = self.accrued_loan(caller)?;
+            loan.collateral = loan.collateral.checked_add(amount).ok_or(Error::Overflow)?;
+
+            let contract = self.env().account_id();
+            self.transfer_from_to(caller, contract, amount)?;
+            self.store_loan(caller, &loan);
+
+            self.env().emit_event(CollateralDeposited {
+                account: caller,
+                amount,
+            });
+            Ok(())
+        }
+
+        /// Unlock `amount` of collateral, as long as the remaining debt stays within
+        /// the loan-to-value ratio
+        #[ink(message)]
+        pub fn withdraw_collateral(&mut self, amount: Balance) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            let mut loan = self.loans.get(caller).ok_or(Error::NoLoan)?;
+            loan = self.accrue(loan)?;
+            loan.collateral = loan
+                .collateral
+                .checked_sub(amount)
+                .ok_or(Error::InsufficientBalance)?;
+
+            if loan.debt > self.borrow_limit(loan.collateral)? {
+                return Err(Error::Undercollateralised);
+            }
+
+            let contract = self.env().account_id();
+            self.transfer_from_to(contract, caller, amount)?;
+            self.store_loan(caller, &loan);
+
+            self.env().emit_event(CollateralWithdrawn {
+                account: caller,
+                amount,
+            });
+            Ok(())
+        }
+
+        /// Borrow `amount` of native currency from the mint revenue against the
+        /// caller's collateral
+        #[ink(message)]
+        pub fn borrow(&mut self, amount: Balance) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            self.ensure_not_paused()?;
+            self.ensure_not_sunset()?;
+
+            if self.loan_to_value_bps() == 0 {
+                return Err(Error::LendingDisabled);
+            }
+
+            let mut loan = self.loans.get(caller).ok_or(Error::NoLoan)?;
+            loan = self.accrue(loan)?;
+            loan.debt = loan.debt.checked_add(amount).ok_or(Error::Overflow)?;
+
+            if loan.debt > self.borrow_limit(loan.collateral)? {
+                return Err(Error::Undercollateralised);
+            }
+
+            let reserve = self
+                .contract_balance()
+                .saturating_sub(self.env().minimum_balance())
+                .saturating_sub(self.committed_payments());
+            if amount > reserve {
+                return Err(Error::InsufficientReserve);
+            }
+
+            self.store_loan(caller, &loan);
+
+            if self.env().transfer(caller, amount).is_err() {
+                return Err(Error::TransferFailed);
+            }
+
+            self.env().emit_event(Borrowed {
+                account: caller,
+                amount,
+            });
+            Ok(())
+        }
+
+        /// Pay back debt with the transferred value; any excess is refunded
+        #[ink(message, payable)]
+        pub fn repay(&mut self) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+            let payment = self.env().transferred_value();
+
+            let mut loan = self.loans.get(caller).ok_or(Error::NoLoan)?;
+            loan = self.accrue(loan)?;
+
+            if loan.debt == 0 {
+                return Err(Error::NothingToRepay);
+            }
+
+            let repaid = payment.min(loan.debt);
+            loan.debt = loan.debt.saturating_sub(repaid);
+            self.store_loan(caller, &loan);
+
+            let excess = payment.saturating_sub(repaid);
+            if excess > 0 && self.env().transfer(caller, excess).is_err() {
+                return Err(Error::TransferFailed);
+            }
+
+            self.env().emit_event(Repaid {
+                account: caller,
+                amount: repaid,
+            });
+            Ok(())
+        }
+
+        /// Close an undercollateralised loan: collateral worth the debt at the
+        /// `min_payment`/`mint_amount` price goes to the owner, the rest back to the
+        /// borrower, and the debt is written off.
+        #[ink(message)]
+        pub fn liquidate(&mut self, borrower: AccountId) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            let mut loan = self.loans.get(borrower).ok_or(Error::NoLoan)?;
+            loan = self.accrue(loan)?;
+
+            if !self.loan_liquidatable(&loan)? {
+                return Err(Error::NotLiquidatable);
+            }
+
+            let seized = self.collateral_for(loan.debt)?.min(loan.collateral);
+            let returned = loan.collateral.saturating_sub(seized);
+
+            let contract = self.env().account_id();
+            self.transfer_from_to(contract, caller, seized)?;
+            if returned > 0 {
+                self.transfer_from_to(contract, borrower, returned)?;
+            }
+            self.store_loan(borrower, &Loan::default());
+
+            self.env().emit_event(Liquidated {
+                borrower,
+                debt: loan.debt,
+                seized,
+            });
+            Ok(())
+        }
+
+        /// Loan of `account` with interest accrued up to the current block
+        #[ink(message)]
+        pub fn loan(&self, account: AccountId) -> Result<Option<Loan>> {
+            match self.loans.get(account) {
+                Some(loan) => Ok(Some(self.accrue(loan)?)),
+                None => Ok(None),
+            }
+        }
+
+        /// Number of accounts with collateral or debt
+        #[ink(message)]
+        pub fn open_loans(&self) -> u32 {
+            self.open_loans.get().unwrap_or_default()
+        }
+
+        /// Native value of `amount` tokens at the `min_payment`/`mint_amount` price
+        #[ink(message)]
+        pub fn collateral_value(&self, amount: Balance) -> Result<Balance> {
+            let mint_amount = self.mint_amount();
+            if mint_amount == 0 {
+                return Err(Error::LendingDisabled);
+            }
+            Self::mul_div(amount, self.min_payment(), mint_amount).ok_or(Error::Overflow)
+        }
+
+        #[ink(message)]
+        pub fn is_liquidatable(&self, account: AccountId) -> Result<bool> {
+            match self.loan(account)? {
+                Some(loan) => self.loan_liquidatable(&loan),
+                None => Ok(false),
+            }
+        }
+
+        #[ink(message)]
+        pub fn loan_to_value_bps(&self) -> u16 {
+            self.loan_to_value_bps.get().unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn liquidation_threshold_bps(&self) -> u16 {
+            self.liquidation_threshold_bps.get().unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn interest_rate_ppb(&self) -> u32 {
+            self.interest_rate_ppb.get().unwrap_or_default()
+        }
+
+        /// Allow owner to configure lending (a loan-to-value of 0 disables new
+        /// deposits and borrows)
+        #[ink(message)]
+        pub fn set_lending_config(
+            &mut self,
+            loan_to_value_bps: u16,
+            liquidation_threshold_bps: u16,
+            interest_rate_ppb: u32,
+        ) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            if loan_to_value_bps > liquidation_threshold_bps || liquidation_threshold_bps > 10_000 {
+                return Err(Error::InvalidLendingConfig);
+            }
+
+            self.loan_to_value_bps.set(&loan_to_value_bps);
+            self.liquidation_threshold_bps
+                .set(&liquidation_threshold_bps);
+            self.interest_rate_ppb.set(&interest_rate_ppb);
+            self.env().emit_event(LendingConfigUpdated {
+                loan_to_value_bps,
+                liquidation_threshold_bps,
+                interest_rate_ppb,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn withdraw(&mut self) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            if self.paused && !self.withdraw_when_paused {
+                return Err(Error::ContractPaused);
+            }
+
+            if !self.payees().is_empty() {
+                return Err(Error::PaymentSplitActive);
+            }
+
+            self.ensure_not_sunset()?;
+
+            let balance = self.env().balance();
+            let minimum_balance = self.env().minimum_balance();
+
+            if balance <= minimum_balance {
+                return Err(Error::NoFundsToWithdraw);
+            }
+
+            let withdraw_amount = balance
+                .saturating_sub(minimum_balance)
+                .saturating_sub(self.committed_payments());
+
+            if withdraw_amount == 0 {
+                return Err(Error::NoFundsToWithdraw);
+            }
+
+            // Use match instead of is_err() for better error handling
+            match self.env().transfer(caller, withdraw_amount) {
+                Ok(_) => {
+                    self.env().emit_event(Withdraw {
+                        owner: caller,
+                        amount: withdraw_amount,
+                    });
+                    Ok(())
+                }
+                Err(_) => Err(Error::TransferFailed),
+            }
+        }
+
+        /// Destroy `amount` of the caller's tokens without any payout
+        #[ink(message)]
+        pub fn burn(&mut self, amount: Balance) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.sender()?;
+            self.burn_from(caller, amount)
+        }
+
+        /// Burn `amount` of the caller's tokens and pay back native currency
+        /// at `redemption_rate`, out of the contract reserve
+        #[ink(message)]
+        pub fn redeem(&mut self, amount: Balance) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if self.redemption_rate() == 0 {
+                return Err(Error::RedemptionDisabled);
+            }
+
+            self.ensure_not_sunset()?;
+
+            let payout = self.redemption_payout(amount)?;
+            let reserve = self
+                .contract_balance()
+                .saturating_sub(self.env().minimum_balance())
+                .saturating_sub(self.committed_payments());
+
+            if payout > reserve {
+                return Err(Error::InsufficientReserve);
+            }
+
+            self.burn_from(caller, amount)?;
+
+            if self.env().transfer(caller, payout).is_err() {
+                return Err(Error::TransferFailed);
+            }
+
+            self.env().emit_event(Redeem {
+                from: caller,
+                amount,
+                payout,
+            });
+
+            Ok(())
+        }
+
+        /// Native amount that redeeming `amount` tokens would currently pay out
+        #[ink(message)]
+        pub fn redemption_payout(&self, amount: Balance) -> Result<Balance> {
+            let unit = 10u128
+                .checked_pow(u32::from(self.token_decimals()))
+                .ok_or(Error::Overflow)?;
+            let payout = amount
+                .checked_mul(self.redemption_rate())
+                .ok_or(Error::Overflow)?;
+            Ok(payout / unit)
+        }
+
+        /// Transfers `value` tokens to `to`, then calls `PSP22Receiver::before_received`
+        /// on it with `data`. The transfer is reverted if the hook rejects it; plain
+        /// accounts without code receive the tokens as with `transfer`.
+        #[ink(message)]
+        pub fn transfer_and_call(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            data: Vec<u8>,
+        ) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let from = self.sender()?;
+            self.transfer_from_to(from, to, value)?;
+            self.notify_receiver(from, to, value, data)
+        }
+
+        /// Sets `spender`'s allowance over `owner`'s tokens from an off-chain signature,
+        /// so a relayer can pay the fee for the approval.
+        ///
+        /// `signature` is a 65-byte recoverable secp256k1 signature over
+        /// `permit_hash(owner, spender, value, deadline)`; `owner` must be the ECDSA
+        /// account `blake2_256(compressed_public_key)`. sr25519 verification is an
+        /// unstable host function on Astar/Shiden and is therefore not accepted.
+        #[ink(message)]
+        pub fn permit(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+            deadline: Timestamp,
+            signature: [u8; 65],
+        ) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            if self.env().block_timestamp() > deadline {
+                return Err(Error::PermitExpired);
+            }
+
+            let message_hash = self.permit_hash(owner, spender, value, deadline);
+            let public_key = self
+                .env()
+                .ecdsa_recover(&signature, &message_hash)
+                .map_err(|_| Error::InvalidSignature)?;
+
+            let mut signer = <Blake2x256 as HashOutput>::Type::default();
+            ink::env::hash_bytes::<Blake2x256>(&public_key, &mut signer);
+
+            if AccountId::from(signer) != owner {
+                return Err(Error::InvalidSignature);
+            }
+
+            let nonce = self.nonces(owner).checked_add(1).ok_or(Error::Overflow)?;
+            self.nonces.insert(owner, &nonce);
+            self.approve_from_to(owner, spender, value);
+            Ok(())
+        }
+
+        /// Hash an owner must sign to `permit` `spender` for `value` until `deadline`,
+        /// bound to this contract, `chain_id` and the owner's current nonce.
+        #[ink(message)]
+        pub fn permit_hash(
+            &self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+            deadline: Timestamp,
+        ) -> [u8; 32] {
+            let payload = (
+                PERMIT_DOMAIN,
+                self.chain_id(),
+                self.env().account_id(),
+                owner,
+                spender,
+                value,
+                self.nonces(owner),
+                deadline,
+            );
+            let mut output = <Blake2x256 as HashOutput>::Type::default();
+            ink::env::hash_encoded::<Blake2x256, _>(&payload, &mut output);
+            output
+        }
+
+        #[ink(message)]
+        pub fn nonces(&self, owner: AccountId) -> u64 {
+            self.nonces.get(owner).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn chain_id(&self) -> u32 {
+            self.chain_id.get().unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner.get().unwrap_or(self.legacy_owner)
+        }
+
+        #[ink(message)]
+        pub fn min_payment(&self) -> Balance {
+            self.min_payment.get().unwrap_or(self.legacy_min_payment)
+        }
+
+        #[ink(message)]
+        pub fn mint_amount(&self) -> Balance {
+            self.mint_amount.get().unwrap_or(self.legacy_mint_amount)
+        }
+
+        #[ink(message)]
+        pub fn redemption_rate(&self) -> Balance {
+            self.redemption_rate
+        }
+
+        #[ink(message)]
+        pub fn contract_balance(&self) -> Balance {
+            self.env().balance()
+        }
+
+        /// Existential deposit the contract keeps back on `withdraw`
+        #[ink(message)]
+        pub fn minimum_balance(&self) -> Balance {
+            self.env().minimum_balance()
+        }
+
+        #[ink(message)]
+        pub fn merkle_root(&self) -> Option<[u8; 32]> {
+            self.merkle_root.get().unwrap_or(self.legacy_merkle_root)
+        }
+
+        #[ink(message)]
+        pub fn presale_price(&self) -> Balance {
+            self.presale_price
+        }
+
+        #[ink(message)]
+        pub fn presale_minted(&self, account: AccountId) -> u32 {
+            self.presale_minted.get(account).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn public_mint_open(&self) -> bool {
+            self.public_mint_open
+        }
+
+        /// Pay `payee` its share of the mint revenue not yet released to it.
+        ///
+        /// Revenue is everything above `minimum_balance()` plus what was already
+        /// released, so each payee can pull independently of the others. Anyone may
+        /// trigger a release; the funds always go to `payee`.
+        #[ink(message)]
+        pub fn release(&mut self, payee: AccountId) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            if self.paused && !self.withdraw_when_paused {
+                return Err(Error::ContractPaused);
+            }
+
+            if self.shares(payee) == 0 {
+                return Err(Error::NotPayee);
+            }
+
+            self.ensure_not_sunset()?;
+
+            let amount = self.releasable(payee)?;
+
+            if amount == 0 {
+                return Err(Error::NothingToRelease);
+            }
+
+            let released = self
+                .released(payee)
+                .checked_add(amount)
+                .ok_or(Error::Overflow)?;
+            let total_released = self
+                .total_released()
+                .checked_add(amount)
+                .ok_or(Error::Overflow)?;
+            self.released.insert(payee, &released);
+            self.total_released.set(&total_released);
+
+            if self.env().transfer(payee, amount).is_err() {
+                return Err(Error::TransferFailed);
+            }
+
+            self.env().emit_event(PaymentReleased { to: payee, amount });
+            Ok(())
+        }
+
+        /// Native amount `payee` could `release` right now
+        #[ink(message)]
+        pub fn releasable(&self, payee: AccountId) -> Result<Balance> {
+            let available = self
+                .contract_balance()
+                .saturating_sub(self.env().minimum_balance())
+                .saturating_sub(self.committed_payments());
+            let total_received = available
+                .checked_add(self.total_released())
+                .ok_or(Error::Overflow)?;
+            let entitled = total_received
+                .checked_mul(Balance::from(self.shares(payee)))
+                .ok_or(Error::Overflow)?
+                / 10_000;
+
+            Ok(entitled.saturating_sub(self.released(payee)))
+        }
+
+        #[ink(message)]
+        pub fn payees(&self) -> Vec<(AccountId, u16)> {
+            self.payees.get().unwrap_or_default()
+        }
+
+        /// Share of `payee` in basis points (0 if not a payee)
+        #[ink(message)]
+        pub fn shares(&self, payee: AccountId) -> u16 {
+            self.payees()
+                .iter()
+                .find(|(account, _)| *account == payee)
+                .map(|(_, shares)| *shares)
+                .unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn released(&self, payee: AccountId) -> Balance {
+            self.released.get(payee).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn total_released(&self) -> Balance {
+            self.total_released.get().unwrap_or_default()
+        }
+
+        /// Allow owner to split revenue between payees, replacing `withdraw`.
+        ///
+        /// Shares are in basis points and must sum to 10_000. The split is locked
+        /// once anything has been released, as changing shares afterwards would
+        /// misattribute past revenue.
+        #[ink(message)]
+        pub fn set_payees(&mut self, payees: Vec<(AccountId, u16)>) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            if self.total_released() > 0 {
+                return Err(Error::PayeesLocked);
+            }
+
+            let mut total_shares: u32 = 0;
+            for (index, (account, shares)) in payees.iter().enumerate() {
+                if *shares == 0 || payees[..index].iter().any(|(other, _)| other == account) {
+                    return Err(Error::InvalidShares);
+                }
+                total_shares = total_shares.saturating_add(u32::from(*shares));
+            }
+
+            if !payees.is_empty() && total_shares != 10_000 {
+                return Err(Error::InvalidShares);
+            }
+
+            self.payees.set(&payees);
+            self.env().emit_event(PayeesUpdated { payees });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn sale_start(&self) -> Option<Timestamp> {
+            self.sale_window.get().and_then(|(start, _)| start)
+        }
+
+        #[ink(message)]
+        pub fn sale_end(&self) -> Option<Timestamp> {
+            self.sale_window.get().and_then(|(_, end)| end)
+        }
+
+        /// Whether the public `mint` is open at the current block timestamp.
+        /// `sale_start` is inclusive and `sale_end` exclusive; unset bounds are open.
+        #[ink(message)]
+        pub fn sale_status(&self) -> SaleStatus {
+            let now = self.env().block_timestamp();
+            let (start, end) = self.sale_window.get().unwrap_or_default();
+
+            if start.is_some_and(|start| now < start) {
+                SaleStatus::NotStarted
+            } else if end.is_some_and(|end| now >= end) {
+                SaleStatus::Ended
+            } else {
+                SaleStatus::Active
+            }
+        }
+
+        /// Allow owner to schedule the public sale (`None` leaves a bound open)
+        #[ink(message)]
+        pub fn set_sale_window(
+            &mut self,
+            start: Option<Timestamp>,
+            end: Option<Timestamp>,
+        ) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            if let (Some(start), Some(end)) = (start, end) {
+                if start >= end {
+                    return Err(Error::InvalidSaleWindow);
+                }
+            }
+
+            self.sale_window.set(&(start, end));
+            self.env().emit_event(SaleWindowUpdated { start, end });
+            Ok(())
+        }
+
+        /// Allow owner to set the presale allowlist root (`None` closes the presale).
+        ///
+        /// Opening a presale also closes the public `mint` until the owner reopens
+        /// it with `set_public_mint_open` for the public phase.
+        #[ink(message)]
+        pub fn set_merkle_root(&mut self, root: Option<[u8; 32]>) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            self.merkle_root.set(&root);
+            self.env().emit_event(MerkleRootUpdated { root });
+
+            if root.is_some() && self.public_mint_open {
+                self.public_mint_open = false;
+                self.env().emit_event(PublicMintUpdated { open: false });
+            }
+            Ok(())
+        }
+
+        /// Allow owner to update the presale price
+        #[ink(message)]
+        pub fn set_presale_price(&mut self, new_presale_price: Balance) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            let old = self.presale_price();
+            self.presale_price = new_presale_price;
+            self.env().emit_event(PresalePriceUpdated {
+                old,
+                new: new_presale_price,
+            });
+            Ok(())
+        }
+
+        /// Allow owner to open or close the public mint phase
+        #[ink(message)]
+        pub fn set_public_mint_open(&mut self, open: bool) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            self.public_mint_open = open;
+            self.env().emit_event(PublicMintUpdated { open });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn paused(&self) -> bool {
+            self.paused
+        }
+
+        #[ink(message)]
+        pub fn withdraw_when_paused(&self) -> bool {
+            self.withdraw_when_paused
+        }
+
+        /// Allow owner to stop mint, transfers, burn/redeem and (optionally) withdraw
+        #[ink(message)]
+        pub fn pause(&mut self) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            self.ensure_not_paused()?;
+
+            self.paused = true;
+            self.env().emit_event(Paused { by: caller });
+            Ok(())
+        }
+
+        /// Allow owner to resume normal operation
+        #[ink(message)]
+        pub fn unpause(&mut self) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            self.paused = false;
+            self.env().emit_event(Unpaused { by: caller });
+            Ok(())
+        }
+
+        /// Allow owner to choose whether withdraw stays available during pause
+        #[ink(message)]
+        pub fn set_withdraw_when_paused(&mut self, allowed: bool) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            self.withdraw_when_paused = allowed;
+            self.env().emit_event(WithdrawWhenPausedUpdated { allowed });
+            Ok(())
+        }
+
+        /// Balance of `account` at the end of `block`
+        #[ink(message)]
+        pub fn balance_of_at(&self, account: AccountId, block: BlockNumber) -> Balance {
+            let count = self
+                .balance_checkpoint_count
+                .get(account)
+                .unwrap_or_default();
+            if count == 0 {
+                // untouched since checkpoints were introduced
+                return self.balance_of(account);
+            }
+            Self::checkpoint_lookup(count, block, |index| {
+                self.balance_checkpoints.get((account, index))
+            })
+        }
+
+        /// Total supply at the end of `block`
+        #[ink(message)]
+        pub fn total_supply_at(&self, block: BlockNumber) -> Balance {
+            let count = self.supply_checkpoint_count.get().unwrap_or_default();
+            if count == 0 {
+                return self.total_supply();
+            }
+            Self::checkpoint_lookup(count, block, |index| self.supply_checkpoints.get(index))
+        }
+
+        /// Open a proposal to change a mint parameter.
+        ///
+        /// Voting power is the balance held at the end of the previous block, so
+        /// tokens moved after the proposal is created do not count.
+        #[ink(message)]
+        pub fn propose(&mut self, action: ProposalAction) -> Result<u32> {
+            self.ensure_no_flash_loan()?;
+            let proposer = self.env().caller();
+            let current_block = self.env().block_number();
+            let snapshot_block = current_block.saturating_sub(1);
+
+            if self.balance_of_at(proposer, snapshot_block) == 0 {
+                return Err(Error::NoVotingPower);
+            }
+
+            let end_block = current_block
+                .checked_add(self.voting_period())
+                .ok_or(Error::Overflow)?;
+            let id = self.proposal_count();
+            let proposal_count = id.checked_add(1).ok_or(Error::Overflow)?;
+
+            self.proposals.insert(
+                id,
+                &Proposal {
+                    proposer,
+                    action,
+                    snapshot_block,
+                    end_block,
+                    votes_for: 0,
+                    votes_against: 0,
+                    executed: false,
+                },
+            );
+            self.proposal_count.set(&proposal_count);
+
+            self.env().emit_event(ProposalCreated {
+                id,
+                proposer,
+                action,
+                end_block,
+            });
+            Ok(id)
+        }
+
+        /// Vote on an open proposal with the balance held at its snapshot
+        #[ink(message)]
+        pub fn vote(&mut self, id: u32, support: bool) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let voter = self.env().caller();
+            let mut proposal = self.proposals.get(id).ok_or(Error::ProposalNotFound)?;
+
+            if self.env().block_number() > proposal.end_block {
+                return Err(Error::VotingClosed);
+            }
+            if self.proposal_votes.contains((id, voter)) {
+                return Err(Error::AlreadyVoted);
+            }
+
+            let weight = self.balance_of_at(voter, proposal.snapshot_block);
+            if weight == 0 {
+                return Err(Error::NoVotingPower);
+            }
+
+            if support {
+                proposal.votes_for = proposal
+                    .votes_for
+                    .checked_add(weight)
+                    .ok_or(Error::Overflow)?;
+            } else {
+                proposal.votes_against = proposal
+                    .votes_against
+                    .checked_add(weight)
+                    .ok_or(Error::Overflow)?;
+            }
+
+            self.proposals.insert(id, &proposal);
+            self.proposal_votes.insert((id, voter), &support);
+
+            self.env().emit_event(VoteCast {
+                id,
+                voter,
+                support,
+                weight,
+            });
+            Ok(())
+        }
+
+        /// Apply a proposal once its voting period is over, if it reached quorum
+        /// and the approval threshold. Callable by anyone.
+        #[ink(message)]
+        pub fn execute_proposal(&mut self, id: u32) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let mut proposal = self.proposals.get(id).ok_or(Error::ProposalNotFound)?;
+
+            if proposal.executed {
+                return Err(Error::ProposalAlreadyExecuted);
+            }
+            if self.env().block_number() <= proposal.end_block {
+                return Err(Error::VotingActive);
+            }
+
+            let votes_cast = proposal
+                .votes_for
+                .checked_add(proposal.votes_against)
+                .ok_or(Error::Overflow)?;
+            let quorum = self
+                .total_supply_at(proposal.snapshot_block)
+                .checked_mul(Balance::from(self.quorum_bps()))
+                .ok_or(Error::Overflow)?
+                / 10_000;
+            if votes_cast == 0 || votes_cast < quorum {
+                return Err(Error::QuorumNotReached);
+            }
+
+            let approval = proposal
+                .votes_for
+                .checked_mul(10_000)
+                .ok_or(Error::Overflow)?;
+            let threshold = votes_cast
+                .checked_mul(Balance::from(self.approval_threshold_bps()))
+                .ok_or(Error::Overflow)?;
+            if approval <= threshold {
+                return Err(Error::ProposalRejected);
+            }
+
+            self.apply_action(proposal.action);
+
+            proposal.executed = true;
+            self.proposals.insert(id, &proposal);
+
+            self.env().emit_event(ProposalExecuted {
+                id,
+                action: proposal.action,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn proposal(&self, id: u32) -> Option<Proposal> {
+            self.proposals.get(id)
+        }
+
+        #[ink(message)]
+        pub fn proposal_count(&self) -> u32 {
+            self.proposal_count.get().unwrap_or_default()
+        }
+
+        /// Vote cast by `voter` on proposal `id` (`Some(true)` in favour)
+        #[ink(message)]
+        pub fn vote_of(&self, id: u32, voter: AccountId) -> Option<bool> {
+            self.proposal_votes.get((id, voter))
+        }
+
+        #[ink(message)]
+        pub fn voting_period(&self) -> BlockNumber {
+            self.voting_period.get().unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn quorum_bps(&self) -> u16 {
+            self.quorum_bps.get().unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn approval_threshold_bps(&self) -> u16 {
+            self.approval_threshold_bps.get().unwrap_or_default()
+        }
+
+        /// Allow owner to tune the voting period, quorum and approval threshold
+        #[ink(message)]
+        pub fn set_governance_config(
+            &mut self,
+            voting_period: BlockNumber,
+            quorum_bps: u16,
+            approval_threshold_bps: u16,
+        ) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            if voting_period == 0 || quorum_bps > 10_000 || approval_threshold_bps >= 10_000 {
+                return Err(Error::InvalidGovernanceConfig);
+            }
+
+            self.voting_period.set(&voting_period);
+            self.quorum_bps.set(&quorum_bps);
+            self.approval_threshold_bps.set(&approval_threshold_bps);
+            self.env().emit_event(GovernanceConfigUpdated {
+                voting_period,
+                quorum_bps,
+                approval_threshold_bps,
+            });
+            Ok(())
+        }
+
+        /// Queue a `min_payment` change that can be executed from `eta` on
+        #[ink(message)]
+        pub fn schedule_min_payment(
+            &mut self,
+            new_min_payment: Balance,
+            eta: Timestamp,
+        ) -> Result<u32> {
+            self.ensure_no_flash_loan()?;
+            self.schedule_change(ProposalAction::SetMinPayment(new_min_payment), eta)
+        }
+
+        /// Queue a `mint_amount` change that can be executed from `eta` on
+        #[ink(message)]
+        pub fn schedule_mint_amount(
+            &mut self,
+            new_mint_amount: Balance,
+            eta: Timestamp,
+        ) -> Result<u32> {
+            self.ensure_no_flash_loan()?;
+            self.schedule_change(ProposalAction::SetMintAmount(new_mint_amount), eta)
+        }
+
+        /// Apply a scheduled change once its eta has passed
+        #[ink(message)]
+        pub fn execute_change(&mut self, id: u32) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            let change = self
+                .scheduled_changes
+                .get(id)
+                .ok_or(Error::ChangeNotFound)?;
+            if self.env().block_timestamp() < change.eta {
+                return Err(Error::ChangeNotReady);
+            }
+
+            self.remove_scheduled_change(id);
+            self.apply_action(change.action);
+            self.env().emit_event(ChangeExecuted {
+                id,
+                action: change.action,
+            });
+            Ok(())
+        }
+
+        /// Drop a scheduled change before it is executed
+        #[ink(message)]
+        pub fn cancel_change(&mut self, id: u32) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            if !self.scheduled_changes.contains(id) {
+                return Err(Error::ChangeNotFound);
+            }
+
+            self.remove_scheduled_change(id);
+            self.env().emit_event(ChangeCancelled { id });
+            Ok(())
+        }
+
+        /// Changes scheduled but neither executed nor cancelled, oldest first
+        #[ink(message)]
+        pub fn pending_changes(&self) -> Vec<ScheduledChange> {
+            self.pending_change_ids
+                .get()
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|id| self.scheduled_changes.get(id))
+                .collect()
+        }
+
+        #[ink(message)]
+        pub fn timelock_delay(&self) -> Timestamp {
+            self.timelock_delay.get().unwrap_or_default()
+        }
+
+        /// Allow owner to lengthen the timelock delay (in milliseconds).
+        ///
+        /// While the delay is non-zero, `set_min_payment` and `set_mint_amount` are
+        /// disabled and changes have to be scheduled. The delay can only grow, so
+        /// the owner cannot switch the timelock off again.
+        #[ink(message)]
+        pub fn set_timelock_delay(&mut self, new_timelock_delay: Timestamp) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            let old = self.timelock_delay();
+            if new_timelock_delay < old {
+                return Err(Error::InvalidTimelockDelay);
+            }
+
+            self.timelock_delay.set(&new_timelock_delay);
+            self.env().emit_event(TimelockDelayUpdated {
+                old,
+                new: new_timelock_delay,
+            });
+            Ok(())
+        }
+
+        /// Allow owner to update minimum payment
+        #[ink(message)]
+        pub fn set_min_payment(&mut self, new_min_payment: Balance) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            if self.timelock_delay() > 0 {
+                return Err(Error::TimelockActive);
+            }
+
+            self.update_min_payment(new_min_payment);
+            Ok(())
+        }
+
+        /// Allow owner to update mint amount
+        #[ink(message)]
+        pub fn set_mint_amount(&mut self, new_mint_amount: Balance) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            if self.timelock_delay() > 0 {
+                return Err(Error::TimelockActive);
+            }
+
+            self.update_mint_amount(new_mint_amount);
+            Ok(())
+        }
+
+        /// Allow owner to set the chain identifier used in permit payloads
+        #[ink(message)]
+        pub fn set_chain_id(&mut self, new_chain_id: u32) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            let old = self.chain_id();
+            self.chain_id.set(&new_chain_id);
+            self.env().emit_event(ChainIdUpdated {
+                old,
+                new: new_chain_id,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn trusted_forwarder(&self) -> Option<AccountId> {
+            self.trusted_forwarder.get().flatten()
+        }
+
+        #[ink(message)]
+        pub fn is_trusted_forwarder(&self, forwarder: AccountId) -> bool {
+            self.trusted_forwarder() == Some(forwarder)
+        }
+
+        /// Allow owner to set (or clear) the forwarder whose relayed requests are
+        /// attributed to their signer in mints, transfers, approvals and burns
+        #[ink(message)]
+        pub fn set_trusted_forwarder(&mut self, forwarder: Option<AccountId>) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            let old = self.trusted_forwarder();
+            self.trusted_forwarder.set(&forwarder);
+            self.env().emit_event(TrustedForwarderUpdated {
+                old,
+                new: forwarder,
+            });
+            Ok(())
+        }
+
+        /// Lend `amount` freshly minted tokens to `receiver` for the duration of this
+        /// call (PSP22FlashLender).
+        ///
+        /// `receiver` must implement `FlashBorrower` and, by the time `on_flashloan`
+        /// returns, have approved this contract for `amount + flash_fee(amount)`.
+        /// The principal is burned and the fee is transferred to the owner;
+        /// otherwise the whole loan reverts. While `on_flashloan` runs, every
+        /// other mutating message fails with `FlashLoanActive`.
+        #[ink(message)]
+        pub fn flash_loan(
+            &mut self,
+            receiver: AccountId,
+            amount: Balance,
+            data: Vec<u8>,
+        ) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            self.ensure_not_paused()?;
+
+            let initiator = self.env().caller();
+            let token = self.env().account_id();
+            let fee = self.flash_fee(amount)?;
+            let repayment = amount.checked_add(fee).ok_or(Error::Overflow)?;
+
+            self.mint_tokens(receiver, amount)?;
+
+            let mut borrower: ink::contract_ref!(FlashBorrower) = receiver.into();
+            // the borrower calls back into this contract to approve the repayment;
+            // any other reentrant call would be overwritten when this call stores
+            // its root cell, so the lock turns them away until the loan is settled
+            self.flash_loan_active.set(&true);
+            let result = borrower
+                .call_mut()
+                .on_flashloan(initiator, token, amount, fee, data)
+                .call_flags(CallFlags::ALLOW_REENTRY)
+                .try_invoke();
+            self.flash_loan_active.set(&false);
+
+            match result {
+                Ok(Ok(Ok(()))) => {}
+                Ok(Ok(Err(FlashBorrowerError::FlashloanRejected(reason)))) => {
+                    return Err(Error::FlashLoanRejected(reason))
+                }
+                _ => {
+                    return Err(Error::FlashLoanRejected(String::from(
+                        "Receiver does not implement FlashBorrower",
+                    )))
+                }
+            }
+
+            self.spend_allowance(receiver, token, repayment)?;
+
+            self.burn_from(receiver, amount)?;
+
+            if fee > 0 {
+                let owner = self.owner();
+                self.transfer_from_to(receiver, owner, fee)?;
+            }
+
+            self.env().emit_event(FlashLoan {
+                receiver,
+                initiator,
+                amount,
+                fee,
+            });
+            Ok(())
+        }
+
+        /// Largest amount `flash_loan` can mint without overflowing the supply
+        #[ink(message)]
+        pub fn max_flashloan(&self) -> Balance {
+            Balance::MAX.saturating_sub(self.total_supply())
+        }
+
+        /// Fee charged on a flash loan of `amount`
+        #[ink(message)]
+        pub fn flash_fee(&self, amount: Balance) -> Result<Balance> {
+            let fee = amount
+                .checked_mul(Balance::from(self.flash_fee_bps()))
+                .ok_or(Error::Overflow)?;
+            Ok(fee / 10_000)
+        }
+
+        #[ink(message)]
+        pub fn flash_fee_bps(&self) -> u16 {
+            self.flash_fee_bps.get().unwrap_or_default()
+        }
+
+        /// Allow owner to update the flash loan fee (basis points, at most 10_000)
+        #[ink(message)]
+        pub fn set_flash_fee_bps(&mut self, new_flash_fee_bps: u16) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            if new_flash_fee_bps > 10_000 {
+                return Err(Error::InvalidFee);
+            }
+
+            let old = self.flash_fee_bps();
+            self.flash_fee_bps.set(&new_flash_fee_bps);
+            self.env().emit_event(FlashFeeUpdated {
+                old,
+                new: new_flash_fee_bps,
+            });
+            Ok(())
+        }
+
+        /// Allow owner to update the redemption rate (native amount per whole token)
+        #[ink(message)]
+        pub fn set_redemption_rate(&mut self, new_redemption_rate: Balance) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            let old = self.redemption_rate();
+            self.redemption_rate = new_redemption_rate;
+            self.env().emit_event(RedemptionRateUpdated {
+                old,
+                new: new_redemption_rate,
+            });
+            Ok(())
+        }
+
+        /// Allow owner to replace the contract code, keeping address and storage.
+        ///
+        /// The new code must be able to decode the current root storage cell, so
+        /// existing fields may not be reordered, removed or retyped. New state is
+        /// added as `Mapping`/`Lazy` fields and set up by `migrate`.
+        #[ink(message)]
+        pub fn upgrade(&mut self, code_hash: Hash) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            self.env()
+                .set_code_hash(&code_hash)
+                .map_err(|_| Error::UpgradeFailed)?;
+
+            self.env().emit_event(Upgraded { code_hash });
+            Ok(())
+        }
+
+        /// Allow owner to bring the storage up to `STORAGE_VERSION` after an upgrade,
+        /// applying the layout step of every version above the stored one in order
+        #[ink(message)]
+        pub fn migrate(&mut self) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            let from = self.storage_version;
+
+            if from >= STORAGE_VERSION {
+                return Err(Error::NothingToMigrate);
+            }
+
+            if from < 2 {
+                self.migrate_to_v2();
+            }
+
+            self.storage_version = STORAGE_VERSION;
+
+            self.env().emit_event(Migrated {
+                from,
+                to: STORAGE_VERSION,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn storage_version(&self) -> u32 {
+            self.storage_version
+        }
+
+        /// Retire the token: freeze minting and open a claim period of
+        /// `claim_period` milliseconds in which holders `claim` their share of the
+        /// contract balance. Owner withdrawals, payee releases, lending and
+        /// redemption stop as well.
+        ///
+        /// Every loan has to be repaid and its collateral withdrawn (or
+        /// liquidated) first, so no holder's tokens are locked in the contract
+        /// while the others claim.
+        #[ink(message)]
+        pub fn start_sunset(&mut self, claim_period: Timestamp) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            self.ensure_not_sunset()?;
+
+            if self.open_loans() > 0 {
+                return Err(Error::LoansOpen);
+            }
+
+            let claim_end = self
+                .env()
+                .block_timestamp()
+                .checked_add(claim_period)
+                .ok_or(Error::Overflow)?;
+            self.sunset_claim_end.set(&Some(claim_end));
+
+            self.env().emit_event(SunsetStarted { claim_end });
+            Ok(())
+        }
+
+        /// Burn `amount` of the caller's tokens for their pro-rata share of the
+        /// contract balance during the sunset claim period. Works while paused so
+        /// the owner cannot hold claims back until `terminate_contract`.
+        #[ink(message)]
+        pub fn claim(&mut self, amount: Balance) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+            let claim_end = self.sunset_claim_end().ok_or(Error::SunsetNotStarted)?;
+
+            if self.env().block_timestamp() > claim_end {
+                return Err(Error::ClaimPeriodEnded);
+            }
+
+            let payout = self.claim_payout(amount)?;
+            self.burn_tokens(caller, amount)?;
+
+            if self.env().transfer(caller, payout).is_err() {
+                return Err(Error::TransferFailed);
+            }
+
+            self.env().emit_event(SunsetClaimed {
+                account: caller,
+                amount,
+                payout,
+            });
+            Ok(())
+        }
+
+        /// Native amount `claim` currently pays for `amount` tokens. No collateral is
+        /// locked during the sunset, so tokens held by the contract itself were sent
+        /// to it by mistake; they can never be claimed and do not count.
+        #[ink(message)]
+        pub fn claim_payout(&self, amount: Balance) -> Result<Balance> {
+            let claimable_supply = self
+                .total_supply()
+                .saturating_sub(self.balance_of(self.env().account_id()));
+            if claimable_supply == 0 {
+                return Ok(0);
+            }
+
+            let available = self
+                .contract_balance()
+                .saturating_sub(self.env().minimum_balance())
+                .saturating_sub(self.committed_payments());
+            Self::mul_div(available, amount, claimable_supply).ok_or(Error::Overflow)
+        }
+
+        #[ink(message)]
+        pub fn sunset_claim_end(&self) -> Option<Timestamp> {
+            self.sunset_claim_end.get().flatten()
+        }
+
+        /// Once the claim period is over, remove the contract and send whatever
+        /// is left to the owner
+        #[ink(message)]
+        pub fn terminate_contract(&mut self) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            let claim_end = self.sunset_claim_end().ok_or(Error::SunsetNotStarted)?;
+            if self.env().block_timestamp() <= claim_end {
+                return Err(Error::ClaimPeriodActive);
+            }
+
+            self.env().emit_event(ContractTerminated {
+                beneficiary: caller,
+                residue: self.contract_balance(),
+            });
+            self.env().terminate_contract(caller)
+        }
+
+        /// Transfer ownership to a new owner
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<()> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            self.owner.set(&new_owner);
+            self.env().emit_event(OwnershipTransferred {
+                previous: Some(caller),
+                new: new_owner,
+            });
+            Ok(())
+        }
+
+        /// Move the supply, owner, mint economics, name, symbol and presale root out
+        /// of their version 1 root slots, shrinking the root cell read by every message.
+        fn migrate_to_v2(&mut self) {
+            self.total_supply.set(&self.total_supply());
+            self.owner.set(&self.owner());
+            self.min_payment.set(&self.min_payment());
+            self.mint_amount.set(&self.mint_amount());
+            self.name.set(&self.token_name());
+            self.symbol.set(&self.token_symbol());
+            self.merkle_root.set(&self.merkle_root());
+            self.legacy_total_supply = 0;
+            self.legacy_owner = AccountId::from([0x00; 32]);
+            self.legacy_min_payment = 0;
+            self.legacy_mint_amount = 0;
+            self.legacy_name = None;
+            self.legacy_symbol = None;
+            self.legacy_merkle_root = None;
+        }
+
+        fn mint_to(&mut self, to: AccountId, payment: Balance) -> Result<()> {
+            let mint_amount = self.mint_amount();
+            self.mint_tokens(to, mint_amount)?;
+
+            self.env().emit_event(Mint {
+                to,
+                amount: mint_amount,
+                payment,
+            });
+
+            Ok(())
+        }
+
+        fn mint_tokens(&mut self, to: AccountId, amount: Balance) -> Result<()> {
+            let new_balance = self
+                .balance_of(to)
+                .checked_add(amount)
+                .ok_or(Error::Overflow)?;
+            let new_total_supply = self
+                .total_supply()
+                .checked_add(amount)
+                .ok_or(Error::Overflow)?;
+
+            self.set_balance(to, new_balance)?;
+            self.set_total_supply(new_total_supply)?;
+
+            self.env().emit_event(Transfer {
+                from: None,
+                to: Some(to),
+                value: amount,
+            });
+
+            Ok(())
+        }
+
+        fn apply_action(&mut self, action: ProposalAction) {
+            match action {
+                ProposalAction::SetMinPayment(value) => self.update_min_payment(value),
+                ProposalAction::SetMintAmount(value) => self.update_mint_amount(value),
+            }
+        }
+
+        fn schedule_change(&mut self, action: ProposalAction, eta: Timestamp) -> Result<u32> {
+            let caller = self.env().caller();
+
+            if caller != self.owner() {
+                return Err(Error::NotOwner);
+            }
+
+            let earliest = self
+                .env()
+                .block_timestamp()
+                .checked_add(self.timelock_delay())
+                .ok_or(Error::Overflow)?;
+            if eta < earliest {
+                return Err(Error::EtaTooEarly);
+            }
+
+            let id = self.scheduled_change_count.get().unwrap_or_default();
+            let scheduled_change_count = id.checked_add(1).ok_or(Error::Overflow)?;
+
+            let mut pending = self.pending_change_ids.get().unwrap_or_default();
+            pending.push(id);
+
+            self.scheduled_changes
+                .insert(id, &ScheduledChange { id, action, eta });
+            self.scheduled_change_count.set(&scheduled_change_count);
+            self.pending_change_ids.set(&pending);
+
+            self.env().emit_event(ChangeScheduled { id, action, eta });
+            Ok(id)
+        }
+
+        fn remove_scheduled_change(&mut self, id: u32) {
+            self.scheduled_changes.remove(id);
+
+            let mut pending = self.pending_change_ids.get().unwrap_or_default();
+            pending.retain(|pending_id| *pending_id != id);
+            self.pending_change_ids.set(&pending);
+        }
+
+        fn update_min_payment(&mut self, new_min_payment: Balance) {
+            let old = self.min_payment();
+            self.min_payment.set(&new_min_payment);
+            self.env().emit_event(MinPaymentUpdated {
+                old,
+                new: new_min_payment,
+            });
+        }
+
+        fn update_mint_amount(&mut self, new_mint_amount: Balance) {
+            let old = self.mint_amount();
+            self.mint_amount.set(&new_mint_amount);
+            self.env().emit_event(MintAmountUpdated {
+                old,
+                new: new_mint_amount,
+            });
+        }
+
+        /// Store `balance` for `account` and record it as the account's balance
+        /// at the end of the current block.
+        ///
+        /// A balance held from before checkpoints were introduced (a version 1
+        /// instance) is first recorded at block 0, so earlier snapshots keep it.
+        fn set_balance(&mut self, account: AccountId, balance: Balance) -> Result<()> {
+            let previous = self.balances.get(account).unwrap_or_default();
+            self.balances.insert(account, &balance);
+
+            let block = self.env().block_number();
+            let mut count = self
+                .balance_checkpoint_count
+                .get(account)
+                .unwrap_or_default();
+            if count == 0 && previous > 0 {
+                self.balance_checkpoints
+                    .insert((account, 0), &(0, previous));
+                count = 1;
+                self.balance_checkpoint_count.insert(account, &count);
+            }
+            if let Some(last) = count.checked_sub(1) {
+                if let Some((last_block, _)) = self.balance_checkpoints.get((account, last)) {
+                    if last_block == block {
+                        self.balance_checkpoints
+                            .insert((account, last), &(block, balance));
+                        return Ok(());
+                    }
+                }
+            }
+
+            self.balance_checkpoints
+                .insert((account, count), &(block, balance));
+            let count = count.checked_add(1).ok_or(Error::Overflow)?;
+            self.balance_checkpoint_count.insert(account, &count);
+            Ok(())
+        }
+
+        /// Store the total supply and record it for the current block, seeding
+        /// the history at block 0 with a supply minted before checkpoints existed.
+        fn set_total_supply(&mut self, total_supply: Balance) -> Result<()> {
+            let previous = self.total_supply();
+            self.total_supply.set(&total_supply);
+
+            let block = self.env().block_number();
+            let mut count = self.supply_checkpoint_count.get().unwrap_or_default();
+            if count == 0 && previous > 0 {
+                self.supply_checkpoints.insert(0, &(0, previous));
+                count = 1;
+                self.supply_checkpoint_count.set(&count);
+            }
+            if let Some(last) = count.checked_sub(1) {
+                if let Some((last_block, _)) = self.supply_checkpoints.get(last) {
+                    if last_block == block {
+                        self.supply_checkpoints.insert(last, &(block, total_supply));
+                        return Ok(());
+                    }
+                }
+            }
+
+            self.supply_checkpoints
+                .insert(count, &(block, total_supply));
+            let count = count.checked_add(1).ok_or(Error::Overflow)?;
+            self.supply_checkpoint_count.set(&count);
+            Ok(())
+        }
+
+        /// Value of the last of `count` checkpoints recorded at or before `block`,
+        /// 0 if all of them are later.
+        fn checkpoint_lookup<F>(count: u32, block: BlockNumber, checkpoint: F) -> Balance
+        where
+            F: Fn(u32) -> Option<Checkpoint>,
+        {
+            // binary search for the first checkpoint after `block`
+            let (mut low, mut high) = (0, count);
+            while low < high {
+                let mid = low + (high - low) / 2;
+                match checkpoint(mid) {
+                    Some((checkpoint_block, _)) if checkpoint_block > block => high = mid,
+                    _ => low = mid + 1,
+                }
+            }
+
+            match low.checked_sub(1) {
+                Some(index) => checkpoint(index)
+                    .map(|(_, value)| value)
+                    .unwrap_or_default(),
+                None => 0,
+            }
+        }
+
+        fn allowlist_leaf(account: AccountId, max_allowance: u32) -> [u8; 32] {
+            let mut leaf = <Keccak256 as HashOutput>::Type::default();
+            ink::env::hash_encoded::<Keccak256, _>(&(account, max_allowance), &mut leaf);
+            leaf
+        }
+
+        fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+            let (first, second) = if a <= b { (a, b) } else { (b, a) };
+            let mut input = [0u8; 64];
+            input[..32].copy_from_slice(first);
+            input[32..].copy_from_slice(second);
+
+            let mut output = <Keccak256 as HashOutput>::Type::default();
+            ink::env::hash_bytes::<Keccak256>(&input, &mut output);
+            output
+        }
+
+        fn verify_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+            let computed = proof
+                .iter()
+                .fold(leaf, |node, sibling| Self::hash_pair(&node, sibling));
+            computed == root
+        }
+
+        /// `a * b / denominator` rounded down, with the product kept in 256 bits so
+        /// only a quotient that does not fit a `Balance` fails.
+        fn mul_div(a: Balance, b: Balance, denominator: Balance) -> Option<Balance> {
+            const LOW: Balance = u64::MAX as Balance;
+
+            if denominator == 0 {
+                return None;
+            }
+
+            // 256 bit product as (high, low) halves
+            let (a_high, a_low) = (a >> 64, a & LOW);
+            let (b_high, b_low) = (b >> 64, b & LOW);
+            let low_low = a_low * b_low;
+            let high_low = a_high * b_low;
+            let low_high = a_low * b_high;
+            let cross = (low_low >> 64) + (high_low & LOW) + (low_high & LOW);
+            let low = (cross << 64) | (low_low & LOW);
+            let high = a_high * b_high + (high_low >> 64) + (low_high >> 64) + (cross >> 64);
+
+            if high == 0 {
+                return Some(low / denominator);
+            }
+            if high >= denominator {
+                return None;
+            }
+
+            // long division of (high, low), keeping the remainder below `denominator`
+            let mut remainder = high;
+            let mut quotient: Balance = 0;
+            for bit in (0..128).rev() {
+                let carry = remainder >> 127;
+                remainder = (remainder << 1) | ((low >> bit) & 1);
+                quotient <<= 1;
+                if carry == 1 || remainder >= denominator {
+                    remainder = remainder.wrapping_sub(denominator);
+                    quotient |= 1;
+                }
+            }
+            Some(quotient)
+        }
+
+        fn notify_receiver(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            data: Vec<u8>,
+        ) -> Result<()> {
+            let operator = self.sender()?;
+            let mut receiver: ink::contract_ref!(PSP22Receiver) = to.into();
+
+            match receiver
+                .call_mut()
+                .before_received(from, operator, value, data)
+                .try_invoke()
+            {
+                Ok(Ok(Ok(()))) => Ok(()),
+                Ok(Ok(Err(PSP22ReceiverError::TransferRejected(reason)))) => {
+                    Err(Error::SafeTransferCheckFailed(reason))
+                }
+                Ok(Err(_)) => Err(Error::SafeTransferCheckFailed(String::from(
+                    "Receiver does not implement PSP22Receiver",
+                ))),
+                Err(ink::env::Error::ReturnError(ReturnErrorCode::NotCallable)) => Ok(()),
+                Err(_) => Err(Error::SafeTransferCheckFailed(String::from(
+                    "Receiver hook call failed",
+                ))),
+            }
+        }
+
+        /// Account a message acts for: the signer of the relayed request when called
+        /// by the trusted forwarder, the caller otherwise. Never falls back to the
+        /// forwarder's own account when the signer cannot be read.
+        fn sender(&self) -> Result<AccountId> {
+            let caller = self.env().caller();
+            if !self.is_trusted_forwarder(caller) {
+                return Ok(caller);
+            }
+
+            let forwarder: ink::contract_ref!(TrustedForwarder) = caller.into();
+            match forwarder.call().msg_sender().try_invoke() {
+                Ok(Ok(Some(signer))) => Ok(signer),
+                _ => Err(Error::UnknownSender),
+            }
+        }
+
+        /// Loan of `account` accrued to the current block, empty if there is none
+        fn accrued_loan(&self, account: AccountId) -> Result<Loan> {
+            match self.loans.get(account) {
+                Some(loan) => self.accrue(loan),
+                None => Ok(Loan {
+                    last_accrual: self.env().block_number(),
+                    ..Loan::default()
+                }),
+            }
+        }
+
+        /// Add the interest since `loan.last_accrual` to its debt
+        fn accrue(&self, mut loan: Loan) -> Result<Loan> {
+            let current_block = self.env().block_number();
+            let blocks = current_block.saturating_sub(loan.last_accrual);
+            let rate = Balance::from(self.interest_rate_ppb())
+                .checked_mul(Balance::from(blocks))
+                .ok_or(Error::Overflow)?;
+            let interest = Self::mul_div(loan.debt, rate, INTEREST_SCALE).ok_or(Error::Overflow)?;
+
+            loan.debt = loan.debt.checked_add(interest).ok_or(Error::Overflow)?;
+            loan.last_accrual = current_block;
+            Ok(loan)
+        }
+
+        /// Store `loan` for `account`, dropping it once settled, and keep
+        /// `open_loans` in step.
+        fn store_loan(&mut self, account: AccountId, loan: &Loan) {
+            let existed = self.loans.contains(account);
+            let open_loans = self.open_loans();
+            if loan.collateral == 0 && loan.debt == 0 {
+                self.loans.remove(account);
+                if existed {
+                    self.open_loans.set(&open_loans.saturating_sub(1));
+                }
+            } else {
+                self.loans.insert(account, loan);
+                if !existed {
+                    self.open_loans.set(&open_loans.saturating_add(1));
+                }
+            }
+        }
+
+        /// Largest debt `collateral` can back
+        fn borrow_limit(&self, collateral: Balance) -> Result<Balance> {
+            let limit = self
+                .collateral_value(collateral)?
+                .checked_mul(Balance::from(self.loan_to_value_bps()))
+                .ok_or(Error::Overflow)?;
+            Ok(limit / 10_000)
+        }
+
+        fn loan_liquidatable(&self, loan: &Loan) -> Result<bool> {
+            let threshold = self
+                .collateral_value(loan.collateral)?
+                .checked_mul(Balance::from(self.liquidation_threshold_bps()))
+                .ok_or(Error::Overflow)?
+                / 10_000;
+            Ok(loan.debt > threshold)
+        }
+
+        /// Tokens worth `value` native currency at the `min_payment`/`mint_amount` price
+        fn collateral_for(&self, value: Balance) -> Result<Balance> {
+            let min_payment = self.min_payment();
+            if min_payment == 0 {
+                return Err(Error::LendingDisabled);
+            }
+            Self::mul_div(value, self.mint_amount(), min_payment).ok_or(Error::Overflow)
+        }
+
+        /// Checks shared by the public `mint` and `commit`
+        fn ensure_public_sale(&self) -> Result<()> {
+            self.ensure_not_paused()?;
+            self.ensure_not_sunset()?;
+
+            if !self.public_mint_open {
+                return Err(Error::PublicMintClosed);
+            }
+
+            match self.sale_status() {
+                SaleStatus::NotStarted => Err(Error::SaleNotStarted),
+                SaleStatus::Ended => Err(Error::SaleEnded),
+                SaleStatus::Active => Ok(()),
+            }
+        }
+
+        fn ensure_not_sunset(&self) -> Result<()> {
+            if self.sunset_claim_end().is_some() {
+                return Err(Error::SunsetActive);
+            }
+            Ok(())
+        }
+
+        fn ensure_no_flash_loan(&self) -> Result<()> {
+            if self.flash_loan_active.get().unwrap_or_default() {
+                return Err(Error::FlashLoanActive);
+            }
+            Ok(())
+        }
+
+        fn ensure_not_paused(&self) -> Result<()> {
+            if self.paused {
+                return Err(Error::ContractPaused);
+            }
+            Ok(())
+        }
+
+        fn transfer_from_to(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            self.ensure_not_paused()?;
+
+            let from_balance = self
+                .balance_of(from)
+                .checked_sub(value)
+                .ok_or(Error::InsufficientBalance)?;
+            self.set_balance(from, from_balance)?;
+
+            let to_balance = self
+                .balance_of(to)
+                .checked_add(value)
+                .ok_or(Error::Overflow)?;
+            self.set_balance(to, to_balance)?;
+
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: Some(to),
+                value,
+            });
+
+            Ok(())
+        }
+
+        fn burn_from(&mut self, from: AccountId, amount: Balance) -> Result<()> {
+            self.ensure_not_paused()?;
+            self.burn_tokens(from, amount)
+        }
+
+        fn burn_tokens(&mut self, from: AccountId, amount: Balance) -> Result<()> {
+            let from_balance = self
+                .balance_of(from)
+                .checked_sub(amount)
+                .ok_or(Error::InsufficientBalance)?;
+            let total_supply = self
+                .total_supply()
+                .checked_sub(amount)
+                .ok_or(Error::Overflow)?;
+
+            self.set_balance(from, from_balance)?;
+            self.set_total_supply(total_supply)?;
+
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: None,
+                value: amount,
+            });
+            self.env().emit_event(Burn { from, amount });
+
+            Ok(())
+        }
+
+        fn approve_from_to(&mut self, owner: AccountId, spender: AccountId, value: Balance) {
+            self.allowances.insert((owner, spender), &value);
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                value,
+            });
+        }
+
+        /// Take `value` out of the allowance `owner` granted to `spender`
+        fn spend_allowance(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+        ) -> Result<()> {
+            let allowance = self
+                .allowance(owner, spender)
+                .checked_sub(value)
+                .ok_or(Error::InsufficientAllowance)?;
+            self.allowances.insert((owner, spender), &allowance);
+            Ok(())
+        }
+    }
+
+    impl PSP22 for IabsToken {
+        #[ink(message)]
+        fn total_supply(&self) -> Balance {
+            self.total_supply.get().unwrap_or(self.legacy_total_supply)
+        }
+
+        #[ink(message)]
+        fn balance_of(&self, owner: AccountId) -> Balance {
+            self.balances.get(owner).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            self.allowances.get((owner, spender)).unwrap_or_default()
+        }
+
+        /// `data` is not passed on; use `transfer_and_call` to notify a receiver contract.
+        #[ink(message)]
+        fn transfer(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> core::result::Result<(), PSP22Error> {
+            self.ensure_no_flash_loan()?;
+            let from = self.sender()?;
+            self.transfer_from_to(from, to, value)?;
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> core::result::Result<(), PSP22Error> {
+            self.ensure_no_flash_loan()?;
+            let caller = self.sender()?;
+            self.spend_allowance(from, caller, value)?;
+            self.transfer_from_to(from, to, value)?;
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn approve(
+            &mut self,
+            spender: AccountId,
+            value: Balance,
+        ) -> core::result::Result<(), PSP22Error> {
+            let owner = self.sender()?;
+            self.approve_from_to(owner, spender, value);
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn increase_allowance(
+            &mut self,
+            spender: AccountId,
+            delta_value: Balance,
+        ) -> core::result::Result<(), PSP22Error> {
+            self.ensure_no_flash_loan()?;
+            let owner = self.sender()?;
+            let allowance = self
+                .allowance(owner, spender)
+                .checked_add(delta_value)
+                .ok_or(Error::Overflow)?;
+            self.approve_from_to(owner, spender, allowance);
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn decrease_allowance(
+            &mut self,
+            spender: AccountId,
+            delta_value: Balance,
+        ) -> core::result::Result<(), PSP22Error> {
+            self.ensure_no_flash_loan()?;
+            let owner = self.sender()?;
+            let allowance = self
+                .allowance(owner, spender)
+                .checked_sub(delta_value)
+                .ok_or(Error::InsufficientAllowance)?;
+            self.approve_from_to(owner, spender, allowance);
+            Ok(())
+        }
+    }
+
+    impl PSP22Metadata for IabsToken {
+        #[ink(message)]
+        fn token_name(&self) -> Option<String> {
+            self.name.get().unwrap_or_else(|| self.legacy_name.clone())
+        }
+
+        #[ink(message)]
+        fn token_symbol(&self) -> Option<String> {
+            self.symbol
+                .get()
+                .unwrap_or_else(|| self.legacy_symbol.clone())
+        }
+
+        #[ink(message)]
+        fn token_decimals(&self) -> u8 {
+            self.decimals
+        }
+    }
+
+    impl From<Error> for PSP22Error {
+        fn from(error: Error) -> Self {
+            match error {
+                Error::InsufficientBalance => PSP22Error::InsufficientBalance,
+                Error::InsufficientAllowance => PSP22Error::InsufficientAllowance,
+                Error::SafeTransferCheckFailed(reason) => {
+                    PSP22Error::SafeTransferCheckFailed(reason)
+                }
+                other => PSP22Error::Custom(format!("{:?}", other)),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[ink::test]
         fn new_works() {
             let contract = IabsToken::new();
             assert_eq!(contract.total_supply(), 0);
             assert_eq!(contract.min_payment(), 10_000_000_000_000_000);
             assert_eq!(contract.mint_amount(), 1_000_000_000_000_000_000_000);
+            assert_eq!(contract.token_name(), Some(String::from("IabsToken")));
+            assert_eq!(contract.token_symbol(), Some(String::from("IABS")));
+            assert_eq!(contract.token_decimals(), 18);
+        }
+
+        #[ink::test]
+        fn new_with_config_works() {
+            let contract = IabsToken::new_with_config(
+                Some(String::from("Community")),
+                Some(String::from("COM")),
+                12,
+                5_000,
+                42_000,
+            );
+            assert_eq!(contract.token_name(), Some(String::from("Community")));
+            assert_eq!(contract.token_symbol(), Some(String::from("COM")));
+            assert_eq!(contract.token_decimals(), 12);
+            assert_eq!(contract.min_payment(), 5_000);
+            assert_eq!(contract.mint_amount(), 42_000);
+        }
+
+        #[ink::test]
+        fn balance_of_works() {
+            let contract = IabsToken::new();
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            assert_eq!(contract.balance_of(accounts.alice), 0);
+        }
+
+        #[ink::test]
+        fn transfer_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(
+                contract.min_payment(),
+            );
+            assert_eq!(contract.mint(), Ok(()));
+            let minted = contract.mint_amount();
+
+            assert_eq!(contract.transfer(accounts.bob, 10, Vec::new()), Ok(()));
+            assert_eq!(contract.balance_of(accounts.alice), minted - 10);
+            assert_eq!(contract.balance_of(accounts.bob), 10);
+            assert_eq!(contract.total_supply(), minted);
+
+            assert_eq!(
+                contract.transfer(accounts.bob, minted, Vec::new()),
+                Err(PSP22Error::InsufficientBalance)
+            );
+        }
+
+        #[ink::test]
+        fn transfer_from_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(
+                contract.min_payment(),
+            );
+            assert_eq!(contract.mint(), Ok(()));
+            assert_eq!(contract.approve(accounts.bob, 100), Ok(()));
+            assert_eq!(contract.increase_allowance(accounts.bob, 50), Ok(()));
+            assert_eq!(contract.decrease_allowance(accounts.bob, 30), Ok(()));
+            assert_eq!(contract.allowance(accounts.alice, accounts.bob), 120);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                contract.transfer_from(accounts.alice, accounts.charlie, 121, Vec::new()),
+                Err(PSP22Error::InsufficientAllowance)
+            );
+            assert_eq!(
+                contract.transfer_from(accounts.alice, accounts.charlie, 120, Vec::new()),
+                Ok(())
+            );
+            assert_eq!(contract.balance_of(accounts.charlie), 120);
+            assert_eq!(contract.allowance(accounts.alice, accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn mint_overflow_fails() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+
+            assert_eq!(contract.set_mint_amount(Balance::MAX), Ok(()));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(
+                contract.min_payment(),
+            );
+            assert_eq!(contract.mint(), Ok(()));
+            assert_eq!(contract.mint(), Err(Error::Overflow));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.mint(), Err(Error::Overflow));
+
+            assert_eq!(contract.balance_of(accounts.alice), Balance::MAX);
+            assert_eq!(contract.balance_of(accounts.bob), 0);
+            assert_eq!(contract.total_supply(), Balance::MAX);
+        }
+
+        #[ink::test]
+        fn increase_allowance_overflow_fails() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+
+            assert_eq!(contract.approve(accounts.bob, Balance::MAX), Ok(()));
+            assert_eq!(
+                contract.increase_allowance(accounts.bob, 1),
+                Err(PSP22Error::Custom(String::from("Overflow")))
+            );
+            assert_eq!(
+                contract.allowance(accounts.alice, accounts.bob),
+                Balance::MAX
+            );
+        }
+
+        #[ink::test]
+        fn permit_works() {
+            use secp256k1::{Message, SecretKey, SECP256K1};
+
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+            assert_eq!(contract.set_chain_id(2006), Ok(()));
+
+            let secret_key = SecretKey::from_slice(&[7u8; 32]).unwrap();
+            let public_key = secret_key.public_key(SECP256K1).serialize();
+            let mut owner = [0u8; 32];
+            ink::env::hash_bytes::<Blake2x256>(&public_key, &mut owner);
+            let owner = AccountId::from(owner);
+
+            let sign = |hash: [u8; 32]| {
+                let (recovery_id, rs) = SECP256K1
+                    .sign_ecdsa_recoverable(&Message::from_digest(hash), &secret_key)
+                    .serialize_compact();
+                let mut signature = [0u8; 65];
+                signature[..64].copy_from_slice(&rs);
+                signature[64] = recovery_id.to_i32() as u8;
+                signature
+            };
+
+            let deadline = 1_000;
+            let signature = sign(contract.permit_hash(owner, accounts.bob, 500, deadline));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                contract.permit(owner, accounts.bob, 501, deadline, signature),
+                Err(Error::InvalidSignature)
+            );
+            assert_eq!(
+                contract.permit(owner, accounts.bob, 500, deadline, signature),
+                Ok(())
+            );
+            assert_eq!(contract.allowance(owner, accounts.bob), 500);
+            assert_eq!(contract.nonces(owner), 1);
+
+            // the nonce moved on, so the same signature cannot be replayed
+            assert_eq!(
+                contract.permit(owner, accounts.bob, 500, deadline, signature),
+                Err(Error::InvalidSignature)
+            );
+
+            let signature = sign(contract.permit_hash(owner, accounts.bob, 0, deadline));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(deadline + 1);
+            assert_eq!(
+                contract.permit(owner, accounts.bob, 0, deadline, signature),
+                Err(Error::PermitExpired)
+            );
+        }
+
+        #[ink::test]
+        fn burn_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(
+                contract.min_payment(),
+            );
+            assert_eq!(contract.mint(), Ok(()));
+            let minted = contract.mint_amount();
+
+            assert_eq!(contract.burn(minted / 2), Ok(()));
+            assert_eq!(contract.balance_of(accounts.alice), minted / 2);
+            assert_eq!(contract.total_supply(), minted / 2);
+            assert_eq!(contract.burn(minted), Err(Error::InsufficientBalance));
+        }
+
+        #[ink::test]
+        fn redeem_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let contract_id = ink::env::test::callee::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(
+                contract.min_payment(),
+            );
+            assert_eq!(contract.mint(), Ok(()));
+            let minted = contract.mint_amount();
+            assert_eq!(contract.redeem(minted), Err(Error::RedemptionDisabled));
+
+            // 0.00001 SBY per token, i.e. the mint price
+            assert_eq!(contract.set_redemption_rate(10_000_000_000_000), Ok(()));
+            assert_eq!(
+                contract.redemption_payout(minted),
+                Ok(contract.min_payment())
+            );
+
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                contract_id,
+                contract.min_payment(),
+            );
+            assert_eq!(contract.redeem(minted), Err(Error::InsufficientReserve));
+
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                contract_id,
+                contract.min_payment() * 2,
+            );
+            let alice_before =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.alice)
+                    .unwrap();
+            assert_eq!(contract.redeem(minted), Ok(()));
+            assert_eq!(contract.balance_of(accounts.alice), 0);
+            assert_eq!(contract.total_supply(), 0);
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.alice),
+                Ok(alice_before + contract.min_payment())
+            );
+        }
+
+        #[ink::test]
+        fn pause_blocks_mint_and_transfers() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(
+                contract.min_payment(),
+            );
+            assert_eq!(contract.mint(), Ok(()));
+            assert_eq!(contract.approve(accounts.bob, 10), Ok(()));
+
+            assert_eq!(contract.pause(), Ok(()));
+            assert!(contract.paused());
+            assert_eq!(contract.pause(), Err(Error::ContractPaused));
+
+            assert_eq!(contract.mint(), Err(Error::ContractPaused));
+            assert_eq!(
+                contract.transfer(accounts.bob, 1, Vec::new()),
+                Err(PSP22Error::Custom(String::from("ContractPaused")))
+            );
+            assert_eq!(contract.burn(1), Err(Error::ContractPaused));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                contract.transfer_from(accounts.alice, accounts.bob, 1, Vec::new()),
+                Err(PSP22Error::Custom(String::from("ContractPaused")))
+            );
+            assert_eq!(contract.unpause(), Err(Error::NotOwner));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.unpause(), Ok(()));
+            assert!(!contract.paused());
+            assert_eq!(contract.transfer(accounts.bob, 1, Vec::new()), Ok(()));
+        }
+
+        #[ink::test]
+        fn pause_optionally_blocks_withdraw() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let contract_id = ink::env::test::callee::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                contract_id,
+                contract.min_payment() * 10,
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.pause(), Err(Error::NotOwner));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.pause(), Ok(()));
+            assert_eq!(contract.set_withdraw_when_paused(false), Ok(()));
+            assert_eq!(contract.withdraw(), Err(Error::ContractPaused));
+
+            assert_eq!(contract.set_withdraw_when_paused(true), Ok(()));
+            assert_eq!(contract.withdraw(), Ok(()));
+        }
+
+        #[ink::test]
+        fn admin_setters_emit_events() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+
+            assert_eq!(contract.set_min_payment(1), Ok(()));
+            assert_eq!(contract.set_mint_amount(2), Ok(()));
+            assert_eq!(contract.transfer_ownership(accounts.bob), Ok(()));
+
+            let events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(events.len(), 4);
+
+            let constructed =
+                <OwnershipTransferred as ink::scale::Decode>::decode(&mut &events[0].data[..])
+                    .unwrap();
+            assert_eq!(constructed.previous, None);
+            assert_eq!(constructed.new, accounts.alice);
+
+            let min_payment =
+                <MinPaymentUpdated as ink::scale::Decode>::decode(&mut &events[1].data[..])
+                    .unwrap();
+            assert_eq!(min_payment.old, 10_000_000_000_000_000);
+            assert_eq!(min_payment.new, 1);
+
+            let mint_amount =
+                <MintAmountUpdated as ink::scale::Decode>::decode(&mut &events[2].data[..])
+                    .unwrap();
+            assert_eq!(mint_amount.old, 1_000_000_000_000_000_000_000);
+            assert_eq!(mint_amount.new, 2);
+
+            let ownership =
+                <OwnershipTransferred as ink::scale::Decode>::decode(&mut &events[3].data[..])
+                    .unwrap();
+            assert_eq!(ownership.previous, Some(accounts.alice));
+            assert_eq!(ownership.new, accounts.bob);
+        }
+
+        #[ink::test]
+        fn mint_allowlisted_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+
+            let alice_leaf = IabsToken::allowlist_leaf(accounts.alice, 2);
+            let bob_leaf = IabsToken::allowlist_leaf(accounts.bob, 1);
+            let root = IabsToken::hash_pair(&alice_leaf, &bob_leaf);
+
+            assert_eq!(contract.set_presale_price(1_000), Ok(()));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(1_000);
+
+            assert_eq!(
+                contract.mint_allowlisted(vec![bob_leaf], 2),
+                Err(Error::PresaleNotActive)
+            );
+
+            // opening the presale closes the public mint
+            assert!(contract.public_mint_open());
+            assert_eq!(contract.set_merkle_root(Some(root)), Ok(()));
+            assert!(!contract.public_mint_open());
+            assert_eq!(contract.mint(), Err(Error::PublicMintClosed));
+            assert_eq!(
+                contract.mint_allowlisted(vec![bob_leaf], 3),
+                Err(Error::InvalidProof)
+            );
+            assert_eq!(contract.mint_allowlisted(vec![bob_leaf], 2), Ok(()));
+            assert_eq!(contract.mint_allowlisted(vec![bob_leaf], 2), Ok(()));
+            assert_eq!(
+                contract.mint_allowlisted(vec![bob_leaf], 2),
+                Err(Error::PresaleAllowanceExceeded)
+            );
+            assert_eq!(contract.presale_minted(accounts.alice), 2);
+            assert_eq!(
+                contract.balance_of(accounts.alice),
+                contract.mint_amount() * 2
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(999);
+            assert_eq!(
+                contract.mint_allowlisted(vec![alice_leaf], 1),
+                Err(Error::InsufficientPayment)
+            );
+
+            // the owner opens the public phase
+            assert_eq!(contract.set_public_mint_open(true), Err(Error::NotOwner));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.set_public_mint_open(true), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(
+                contract.min_payment(),
+            );
+            assert_eq!(contract.mint(), Ok(()));
+        }
+
+        #[ink::test]
+        fn sale_window_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+            assert_eq!(contract.sale_status(), SaleStatus::Active);
+
+            assert_eq!(
+                contract.set_sale_window(Some(2_000), Some(1_000)),
+                Err(Error::InvalidSaleWindow)
+            );
+            assert_eq!(contract.set_sale_window(Some(1_000), Some(2_000)), Ok(()));
+            assert_eq!(contract.sale_start(), Some(1_000));
+            assert_eq!(contract.sale_end(), Some(2_000));
+
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(
+                contract.min_payment(),
+            );
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(999);
+            assert_eq!(contract.sale_status(), SaleStatus::NotStarted);
+            assert_eq!(contract.mint(), Err(Error::SaleNotStarted));
+
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1_000);
+            assert_eq!(contract.sale_status(), SaleStatus::Active);
+            assert_eq!(contract.mint(), Ok(()));
+
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(2_000);
+            assert_eq!(contract.sale_status(), SaleStatus::Ended);
+            assert_eq!(contract.mint(), Err(Error::SaleEnded));
+            assert_eq!(contract.balance_of(accounts.alice), contract.mint_amount());
+
+            assert_eq!(contract.set_sale_window(Some(1_000), None), Ok(()));
+            assert_eq!(contract.sale_status(), SaleStatus::Active);
+        }
+
+        #[ink::test]
+        fn payment_splitter_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let contract_id = ink::env::test::callee::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+            let minimum_balance = 1_000_000;
+
+            assert_eq!(
+                contract.set_payees(vec![(accounts.bob, 6_000), (accounts.charlie, 3_000)]),
+                Err(Error::InvalidShares)
+            );
+            assert_eq!(
+                contract.set_payees(vec![(accounts.bob, 6_000), (accounts.bob, 4_000)]),
+                Err(Error::InvalidShares)
+            );
+            assert_eq!(
+                contract.set_payees(vec![(accounts.bob, 6_000), (accounts.charlie, 4_000)]),
+                Ok(())
+            );
+
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                contract_id,
+                minimum_balance + 1_000,
+            );
+            assert_eq!(contract.withdraw(), Err(Error::PaymentSplitActive));
+            assert_eq!(contract.release(accounts.django), Err(Error::NotPayee));
+
+            let bob_before =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob)
+                    .unwrap();
+            assert_eq!(contract.releasable(accounts.bob), Ok(600));
+            assert_eq!(contract.release(accounts.bob), Ok(()));
+            assert_eq!(contract.release(accounts.bob), Err(Error::NothingToRelease));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(bob_before + 600)
+            );
+
+            // more revenue comes in, charlie's share is unaffected by bob's release
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                contract_id,
+                minimum_balance + 400 + 1_000,
+            );
+            assert_eq!(contract.releasable(accounts.bob), Ok(600));
+            assert_eq!(contract.releasable(accounts.charlie), Ok(800));
+            assert_eq!(contract.release(accounts.charlie), Ok(()));
+            assert_eq!(contract.released(accounts.charlie), 800);
+            assert_eq!(contract.total_released(), 1_400);
+
+            assert_eq!(
+                contract.set_payees(vec![(accounts.bob, 10_000)]),
+                Err(Error::PayeesLocked)
+            );
+        }
+
+        #[ink::test]
+        fn migrate_empties_legacy_slots() {
+            use ink::storage::traits::{Storable, StorageKey};
+
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+            let min_payment = contract.min_payment();
+            let mint_amount = contract.mint_amount();
+
+            // version 1 instance: supply, owner, economics, metadata and presale
+            // root held in the root cell
+            ink::env::clear_contract_storage(&contract.total_supply.key());
+            ink::env::clear_contract_storage(&contract.owner.key());
+            ink::env::clear_contract_storage(&contract.min_payment.key());
+            ink::env::clear_contract_storage(&contract.mint_amount.key());
+            ink::env::clear_contract_storage(&contract.name.key());
+            ink::env::clear_contract_storage(&contract.symbol.key());
+            ink::env::clear_contract_storage(&contract.merkle_root.key());
+            contract.legacy_total_supply = 0;
+            contract.legacy_owner = accounts.alice;
+            contract.legacy_min_payment = min_payment;
+            contract.legacy_mint_amount = mint_amount;
+            contract.legacy_name = Some(String::from("IabsToken"));
+            contract.legacy_symbol = Some(String::from("IABS"));
+            contract.legacy_merkle_root = Some([0x01; 32]);
+            contract.storage_version = 1;
+
+            assert_eq!(contract.owner(), accounts.alice);
+            assert_eq!(contract.token_name(), Some(String::from("IabsToken")));
+            assert_eq!(contract.token_symbol(), Some(String::from("IABS")));
+            assert_eq!(contract.merkle_root(), Some([0x01; 32]));
+
+            // the upgraded code keeps minting before `migrate` runs
+            assert_eq!(contract.set_public_mint_open(true), Ok(()));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(min_payment);
+            assert_eq!(contract.mint(), Ok(()));
+            assert_eq!(contract.total_supply(), mint_amount);
+
+            let mut legacy_root = Vec::new();
+            contract.encode(&mut legacy_root);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.migrate(), Err(Error::NotOwner));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.migrate(), Ok(()));
+            assert_eq!(contract.storage_version(), STORAGE_VERSION);
+            assert_eq!(contract.total_supply.get(), Some(mint_amount));
+            assert_eq!(contract.owner.get(), Some(accounts.alice));
+            assert_eq!(contract.min_payment.get(), Some(min_payment));
+            assert_eq!(contract.mint_amount.get(), Some(mint_amount));
+            assert_eq!(contract.legacy_owner, AccountId::from([0x00; 32]));
+            assert_eq!(contract.legacy_min_payment, 0);
+            assert_eq!(contract.legacy_mint_amount, 0);
+            assert_eq!(contract.token_name(), Some(String::from("IabsToken")));
+            assert_eq!(contract.token_symbol(), Some(String::from("IABS")));
+            assert_eq!(contract.merkle_root(), Some([0x01; 32]));
+
+            // name and symbol bytes plus the 32 byte root left the root cell
+            let mut root = Vec::new();
+            contract.encode(&mut root);
+            assert_eq!(
+                root.len() + "IabsToken".len() + "IABS".len() + 32 + 2,
+                legacy_root.len()
+            );
+
+            assert_eq!(contract.migrate(), Err(Error::NothingToMigrate));
+        }
+
+        #[ink::test]
+        fn flash_fee_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+
+            assert_eq!(contract.max_flashloan(), Balance::MAX);
+            assert_eq!(contract.flash_fee(1_000_000), Ok(0));
+
+            assert_eq!(contract.set_flash_fee_bps(10_001), Err(Error::InvalidFee));
+            assert_eq!(contract.set_flash_fee_bps(9), Ok(()));
+            assert_eq!(contract.flash_fee(1_000_000), Ok(900));
+            assert_eq!(contract.flash_fee(Balance::MAX), Err(Error::Overflow));
+
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(
+                contract.min_payment(),
+            );
+            assert_eq!(contract.mint(), Ok(()));
+            assert_eq!(
+                contract.max_flashloan(),
+                Balance::MAX - contract.mint_amount()
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.set_flash_fee_bps(1), Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn flash_loan_callback_can_only_approve() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(
+                contract.min_payment(),
+            );
+            assert_eq!(contract.mint(), Ok(()));
+
+            // state seen by a borrower calling back from `on_flashloan`
+            contract.flash_loan_active.set(&true);
+
+            assert_eq!(contract.approve(accounts.bob, 10), Ok(()));
+            assert_eq!(
+                contract.transfer(accounts.bob, 10, Vec::new()),
+                Err(PSP22Error::Custom(String::from("FlashLoanActive")))
+            );
+            assert_eq!(
+                contract.increase_allowance(accounts.bob, 10),
+                Err(PSP22Error::Custom(String::from("FlashLoanActive")))
+            );
+            assert_eq!(contract.mint(), Err(Error::FlashLoanActive));
+            assert_eq!(contract.burn(10), Err(Error::FlashLoanActive));
+            assert_eq!(
+                contract.flash_loan(accounts.bob, 10, Vec::new()),
+                Err(Error::FlashLoanActive)
+            );
+            assert_eq!(contract.pause(), Err(Error::FlashLoanActive));
+            assert_eq!(contract.allowance(accounts.alice, accounts.bob), 10);
+
+            contract.flash_loan_active.set(&false);
+            assert_eq!(contract.transfer(accounts.bob, 10, Vec::new()), Ok(()));
+        }
+
+        #[ink::test]
+        fn balance_checkpoints_work() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+            let mint_amount = contract.mint_amount();
+
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(
+                contract.min_payment(),
+            );
+            assert_eq!(contract.mint(), Ok(()));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(contract.transfer(accounts.bob, 100, Vec::new()), Ok(()));
+            assert_eq!(contract.transfer(accounts.bob, 50, Vec::new()), Ok(()));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            assert_eq!(contract.burn(mint_amount - 150), Ok(()));
+
+            assert_eq!(contract.balance_of_at(accounts.alice, 0), mint_amount);
+            assert_eq!(contract.balance_of_at(accounts.alice, 1), mint_amount - 150);
+            assert_eq!(contract.balance_of_at(accounts.alice, 2), 0);
+            assert_eq!(contract.balance_of_at(accounts.bob, 0), 0);
+            assert_eq!(contract.balance_of_at(accounts.bob, 1), 150);
+            assert_eq!(contract.balance_of_at(accounts.bob, 5), 150);
+            assert_eq!(contract.total_supply_at(1), mint_amount);
+            assert_eq!(contract.total_supply_at(2), 150);
+        }
+
+        #[ink::test]
+        fn checkpoints_keep_balances_from_before_checkpoints() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+            let mint_amount = contract.mint_amount();
+
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(
+                contract.min_payment(),
+            );
+            assert_eq!(contract.mint(), Ok(()));
+
+            // balance and supply of a version 1 instance, without any history
+            contract.balance_checkpoint_count.remove(accounts.alice);
+            contract.supply_checkpoint_count.set(&0);
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+
+            assert_eq!(contract.transfer(accounts.bob, 100, Vec::new()), Ok(()));
+            assert_eq!(contract.burn(50), Ok(()));
+
+            assert_eq!(contract.balance_of_at(accounts.alice, 1), mint_amount);
+            assert_eq!(contract.balance_of_at(accounts.alice, 2), mint_amount - 150);
+            assert_eq!(contract.balance_of_at(accounts.bob, 1), 0);
+            assert_eq!(contract.balance_of_at(accounts.bob, 2), 100);
+            assert_eq!(contract.total_supply_at(1), mint_amount);
+            assert_eq!(contract.total_supply_at(2), mint_amount - 50);
+        }
+
+        #[ink::test]
+        fn governance_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+            let min_payment = contract.min_payment();
+            let mint_amount = contract.mint_amount();
+
+            assert_eq!(
+                contract.set_governance_config(0, 400, 5_000),
+                Err(Error::InvalidGovernanceConfig)
+            );
+            assert_eq!(contract.set_governance_config(10, 5_000, 5_000), Ok(()));
+
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(min_payment);
+            assert_eq!(contract.mint(), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.mint(), Ok(()));
+            assert_eq!(contract.mint(), Ok(()));
+            ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+
+            // charlie has no tokens at the snapshot
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                contract.propose(ProposalAction::SetMintAmount(1)),
+                Err(Error::NoVotingPower)
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.propose(ProposalAction::SetMintAmount(42)), Ok(0));
+            assert_eq!(contract.propose(ProposalAction::SetMinPayment(1)), Ok(1));
+            assert_eq!(contract.proposal_count(), 2);
+
+            // tokens received after the snapshot carry no weight
+            assert_eq!(
+                contract.transfer(accounts.charlie, mint_amount, Vec::new()),
+                Ok(())
+            );
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.vote(0, true), Err(Error::NoVotingPower));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.vote(0, true), Ok(()));
+            assert_eq!(contract.vote(0, false), Err(Error::AlreadyVoted));
+            assert_eq!(contract.vote(2, true), Err(Error::ProposalNotFound));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.vote(0, false), Ok(()));
+            assert_eq!(contract.vote(1, false), Ok(()));
+            assert_eq!(contract.vote_of(0, accounts.bob), Some(true));
+
+            let proposal = contract.proposal(0).unwrap();
+            assert_eq!(proposal.votes_for, 2 * mint_amount);
+            assert_eq!(proposal.votes_against, mint_amount);
+            assert_eq!(contract.execute_proposal(0), Err(Error::VotingActive));
+
+            for _ in 0..=10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+
+            // anyone can execute, no owner involvement needed
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.django);
+            assert_eq!(contract.vote(0, true), Err(Error::VotingClosed));
+            assert_eq!(contract.execute_proposal(0), Ok(()));
+            assert_eq!(contract.mint_amount(), 42);
+            assert_eq!(
+                contract.execute_proposal(0),
+                Err(Error::ProposalAlreadyExecuted)
+            );
+
+            // only alice voted on the second proposal, below the 50% quorum
+            assert_eq!(contract.execute_proposal(1), Err(Error::QuorumNotReached));
+            assert_eq!(contract.min_payment(), min_payment);
+        }
+
+        #[ink::test]
+        fn timelock_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+            let delay = 1_000;
+
+            assert_eq!(contract.set_timelock_delay(delay), Ok(()));
+            assert_eq!(
+                contract.set_timelock_delay(delay - 1),
+                Err(Error::InvalidTimelockDelay)
+            );
+            assert_eq!(contract.set_min_payment(1), Err(Error::TimelockActive));
+            assert_eq!(contract.set_mint_amount(1), Err(Error::TimelockActive));
+
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(500);
+            assert_eq!(
+                contract.schedule_min_payment(7, 500 + delay - 1),
+                Err(Error::EtaTooEarly)
+            );
+            assert_eq!(contract.schedule_min_payment(7, 500 + delay), Ok(0));
+            assert_eq!(contract.schedule_mint_amount(9, 2_000), Ok(1));
+            assert_eq!(
+                contract.pending_changes(),
+                vec![
+                    ScheduledChange {
+                        id: 0,
+                        action: ProposalAction::SetMinPayment(7),
+                        eta: 500 + delay,
+                    },
+                    ScheduledChange {
+                        id: 1,
+                        action: ProposalAction::SetMintAmount(9),
+                        eta: 2_000,
+                    },
+                ]
+            );
+
+            assert_eq!(contract.execute_change(0), Err(Error::ChangeNotReady));
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(500 + delay);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                contract.schedule_min_payment(1, 5_000),
+                Err(Error::NotOwner)
+            );
+            assert_eq!(contract.execute_change(0), Err(Error::NotOwner));
+            assert_eq!(contract.cancel_change(1), Err(Error::NotOwner));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.execute_change(0), Ok(()));
+            assert_eq!(contract.min_payment(), 7);
+            assert_eq!(contract.execute_change(0), Err(Error::ChangeNotFound));
+
+            assert_eq!(contract.cancel_change(1), Ok(()));
+            assert_eq!(contract.cancel_change(1), Err(Error::ChangeNotFound));
+            assert_eq!(contract.pending_changes(), Vec::new());
+            assert_eq!(contract.mint_amount(), 1_000_000_000_000_000_000_000);
         }
 
         #[ink::test]
-        fn balance_of_works() {
-            let contract = IabsToken::new();
+        fn set_trusted_forwarder_works() {
             let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
-            assert_eq!(contract.balance_of(accounts.alice), 0);
+            let mut contract = IabsToken::new();
+
+            assert_eq!(contract.trusted_forwarder(), None);
+            assert_eq!(contract.set_trusted_forwarder(Some(accounts.eve)), Ok(()));
+            assert!(contract.is_trusted_forwarder(accounts.eve));
+            assert!(!contract.is_trusted_forwarder(accounts.alice));
+
+            // direct calls keep acting for the caller
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(
+                contract.min_payment(),
+            );
+            assert_eq!(contract.mint(), Ok(()));
+            assert_eq!(contract.balance_of(accounts.alice), contract.mint_amount());
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.set_trusted_forwarder(None), Err(Error::NotOwner));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.set_trusted_forwarder(None), Ok(()));
+            assert_eq!(contract.trusted_forwarder(), None);
+        }
+
+        #[ink::test]
+        fn set_commit_reveal_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut contract = IabsToken::new();
+
+            assert_eq!(
+                contract.set_commit_reveal(true, 0, 10),
+                Err(Error::InvalidCommitRevealConfig)
+            );
+            assert_eq!(contract.set_commit_reveal(true, 2, 10), Ok(()));
+            assert!(contract.commit_reveal_enabled());
+            assert_eq!(contract.reveal_delay(), 2);
+            assert_eq!(contract.reveal_window(), 10);
+
+            // the public sale now only goes through commit/reveal
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(
+                contract.min_payment(),
+            );
+            assert_eq!(contract.mint(), Err(Error::CommitRevealRequired));
+            assert_eq!(contract.reveal([0x01; 32]), Err(Error::NoCommitment));
+            assert_eq!(contract.refund_commitment(), Err(Error::NoCommitment));
+
+            // commitments are bound to the committing account
+            assert_ne!(
+                contract.commitment_hash(accounts.alice, [0x01; 32]),
+                contract.commitment_hash(accounts.bob, [0x01; 32])
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                contract.set_commit_reveal(false, 0, 0),
+                Err(Error::NotOwner)
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.set_commit_reveal(false, 0, 0), Ok(()));
+            assert_eq!(contract.mint(), Ok(()));
+        }
+
+        #[ink::test]
+        fn lending_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            // keep the contract account apart from the owner's
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+            let contract_id = accounts.frank;
+            let mut contract = IabsToken::new();
+            let min_payment = contract.min_payment();
+            let mint_amount = contract.mint_amount();
+            let minimum_balance = 1_000_000;
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(min_payment);
+            assert_eq!(contract.mint(), Ok(()));
+            assert_eq!(
+                contract.deposit_collateral(mint_amount),
+                Err(Error::LendingDisabled)
+            );
+
+            // 50% loan-to-value, liquidation above 80%, 0.1% interest per block
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                contract.set_lending_config(9_000, 8_000, 1_000_000),
+                Err(Error::InvalidLendingConfig)
+            );
+            assert_eq!(contract.set_lending_config(5_000, 8_000, 1_000_000), Ok(()));
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                contract_id,
+                minimum_balance + min_payment,
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.borrow(1), Err(Error::NoLoan));
+            assert_eq!(contract.deposit_collateral(mint_amount), Ok(()));
+            assert_eq!(contract.balance_of(accounts.bob), 0);
+            assert_eq!(contract.balance_of(contract_id), mint_amount);
+            assert_eq!(contract.collateral_value(mint_amount), Ok(min_payment));
+
+            let limit = min_payment / 2;
+            assert_eq!(contract.borrow(limit + 1), Err(Error::Undercollateralised));
+            let bob_before =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob)
+                    .unwrap();
+            assert_eq!(contract.borrow(limit), Ok(()));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(bob_before + limit)
+            );
+            assert_eq!(
+                contract.withdraw_collateral(1),
+                Err(Error::Undercollateralised)
+            );
+
+            // 10 blocks at 0.1% per block
+            for _ in 0..10 {
+                ink::env::test::advance_block::<ink::env::DefaultEnvironment>();
+            }
+            let debt = limit + limit / 100;
+            assert_eq!(contract.loan(accounts.bob).unwrap().unwrap().debt, debt);
+
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(debt / 2);
+            assert_eq!(contract.repay(), Ok(()));
+            assert_eq!(
+                contract.loan(accounts.bob).unwrap().unwrap().debt,
+                debt - debt / 2
+            );
+            assert_eq!(contract.is_liquidatable(accounts.bob), Ok(false));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                contract.liquidate(accounts.bob),
+                Err(Error::NotLiquidatable)
+            );
+
+            // the price implied by min_payment/mint_amount drops by 70%
+            assert_eq!(contract.set_min_payment(min_payment * 3 / 10), Ok(()));
+            assert_eq!(contract.is_liquidatable(accounts.bob), Ok(true));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.liquidate(accounts.bob), Err(Error::NotOwner));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let remaining_debt = contract.loan(accounts.bob).unwrap().unwrap().debt;
+            let seized = remaining_debt * mint_amount / (min_payment * 3 / 10);
+            assert_eq!(contract.liquidate(accounts.bob), Ok(()));
+            assert_eq!(contract.balance_of(accounts.alice), seized);
+            assert_eq!(contract.balance_of(accounts.bob), mint_amount - seized);
+            assert_eq!(contract.balance_of(contract_id), 0);
+            assert_eq!(contract.loan(accounts.bob), Ok(None));
+            assert_eq!(contract.open_loans(), 0);
+        }
+
+        #[ink::test]
+        fn lending_handles_large_collateral() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+            let contract_id = accounts.frank;
+            let mut contract = IabsToken::new();
+            let min_payment = contract.min_payment();
+            let mint_amount = contract.mint_amount();
+
+            // 100 million IABS, far beyond where `amount * min_payment` overflows
+            let collateral = mint_amount * 100_000;
+            assert_eq!(contract.set_mint_amount(collateral), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(min_payment);
+            assert_eq!(contract.mint(), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.set_mint_amount(mint_amount), Ok(()));
+            assert_eq!(contract.set_lending_config(5_000, 8_000, 0), Ok(()));
+            assert_eq!(
+                contract.collateral_value(Balance::MAX),
+                Ok(Balance::MAX / 100_000)
+            );
+
+            let value = min_payment * 100_000;
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                contract_id,
+                1_000_000 + value,
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.deposit_collateral(collateral), Ok(()));
+            assert_eq!(contract.collateral_value(collateral), Ok(value));
+            assert_eq!(contract.borrow(value / 2), Ok(()));
+            assert_eq!(contract.is_liquidatable(accounts.bob), Ok(false));
+
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(value / 2);
+            assert_eq!(contract.repay(), Ok(()));
+            assert_eq!(contract.withdraw_collateral(collateral), Ok(()));
+            assert_eq!(contract.balance_of(accounts.bob), collateral);
+            assert_eq!(contract.loan(accounts.bob), Ok(None));
+        }
+
+        #[ink::test]
+        fn sunset_works() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+            let contract_id = accounts.frank;
+            let mut contract = IabsToken::new();
+            let min_payment = contract.min_payment();
+            let mint_amount = contract.mint_amount();
+            let minimum_balance = 1_000_000;
+
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(min_payment);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.mint(), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.mint(), Ok(()));
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                contract_id,
+                minimum_balance + 2 * min_payment,
+            );
+
+            assert_eq!(contract.claim(1), Err(Error::SunsetNotStarted));
+            assert_eq!(contract.start_sunset(1_000), Err(Error::NotOwner));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.terminate_contract(), Err(Error::SunsetNotStarted));
+            assert_eq!(contract.start_sunset(1_000), Ok(()));
+            assert_eq!(contract.sunset_claim_end(), Some(1_000));
+            assert_eq!(contract.start_sunset(1_000), Err(Error::SunsetActive));
+            assert_eq!(contract.mint(), Err(Error::SunsetActive));
+            assert_eq!(contract.withdraw(), Err(Error::SunsetActive));
+
+            // bob holds half of the supply and gets half of the balance
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            let bob_before =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob)
+                    .unwrap();
+            assert_eq!(contract.claim_payout(mint_amount), Ok(min_payment));
+            assert_eq!(contract.claim(mint_amount), Ok(()));
+            assert_eq!(contract.balance_of(accounts.bob), 0);
+            assert_eq!(contract.total_supply(), mint_amount);
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(bob_before + min_payment)
+            );
+
+            // the share of the remaining holders is unchanged by earlier claims
+            assert_eq!(contract.claim_payout(mint_amount), Ok(min_payment));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.terminate_contract(), Err(Error::ClaimPeriodActive));
+
+            ink::env::test::set_block_timestamp::<ink::env::DefaultEnvironment>(1_001);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.claim(mint_amount), Err(Error::ClaimPeriodEnded));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            let should_terminate = move || contract.terminate_contract().unwrap();
+            ink::env::test::assert_contract_termination::<ink::env::DefaultEnvironment, _>(
+                should_terminate,
+                accounts.alice,
+                minimum_balance + min_payment,
+            );
+        }
+
+        #[ink::test]
+        fn sunset_claims_scale_to_realistic_supply() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+            let contract_id = accounts.frank;
+            let mut contract = IabsToken::new();
+            let min_payment = contract.min_payment();
+            let mint_amount = contract.mint_amount();
+            let minimum_balance = 1_000_000;
+
+            // a thousand mints' worth of supply split 3:1, backed by their payments
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(min_payment);
+            assert_eq!(contract.set_mint_amount(mint_amount * 750), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.mint(), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.set_mint_amount(mint_amount * 250), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.mint(), Ok(()));
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                contract_id,
+                minimum_balance + 1_000 * min_payment,
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.start_sunset(1_000), Ok(()));
+
+            assert_eq!(
+                contract.claim_payout(mint_amount * 750),
+                Ok(750 * min_payment)
+            );
+            assert_eq!(contract.claim_payout(mint_amount), Ok(min_payment));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            let bob_before =
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob)
+                    .unwrap();
+            assert_eq!(contract.claim(mint_amount * 750), Ok(()));
+            assert_eq!(
+                ink::env::test::get_account_balance::<ink::env::DefaultEnvironment>(accounts.bob),
+                Ok(bob_before + 750 * min_payment)
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.claim(mint_amount * 250), Ok(()));
+            assert_eq!(contract.total_supply(), 0);
+            assert_eq!(contract.contract_balance(), minimum_balance);
+        }
+
+        #[ink::test]
+        fn sunset_waits_for_loans_and_ignores_pause() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            ink::env::test::set_callee::<ink::env::DefaultEnvironment>(accounts.frank);
+            let contract_id = accounts.frank;
+            let mut contract = IabsToken::new();
+            let min_payment = contract.min_payment();
+            let mint_amount = contract.mint_amount();
+            let minimum_balance = 1_000_000;
+
+            assert_eq!(contract.set_lending_config(5_000, 8_000, 0), Ok(()));
+            ink::env::test::set_value_transferred::<ink::env::DefaultEnvironment>(min_payment);
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.mint(), Ok(()));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.mint(), Ok(()));
+            assert_eq!(contract.deposit_collateral(mint_amount / 2), Ok(()));
+            assert_eq!(contract.open_loans(), 1);
+            ink::env::test::set_account_balance::<ink::env::DefaultEnvironment>(
+                contract_id,
+                minimum_balance + 2 * min_payment,
+            );
+
+            // charlie's collateral has to leave the contract first
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.start_sunset(1_000), Err(Error::LoansOpen));
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.withdraw_collateral(mint_amount / 2), Ok(()));
+            assert_eq!(contract.open_loans(), 0);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            assert_eq!(contract.start_sunset(1_000), Ok(()));
+            assert_eq!(contract.pause(), Ok(()));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                contract.deposit_collateral(mint_amount),
+                Err(Error::SunsetActive)
+            );
+
+            // equal holdings get equal payouts, whoever claims first
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.claim_payout(mint_amount), Ok(min_payment));
+            assert_eq!(
+                contract.transfer(accounts.charlie, 1, Vec::new()),
+                Err(PSP22Error::Custom(String::from("ContractPaused")))
+            );
+            assert_eq!(contract.claim(mint_amount), Ok(()));
+            assert_eq!(contract.balance_of(accounts.bob), 0);
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert_eq!(contract.claim_payout(mint_amount), Ok(min_payment));
+            assert_eq!(contract.claim(mint_amount), Ok(()));
+            assert_eq!(contract.contract_balance(), minimum_balance);
+        }
+    }
+
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+        use flash_borrower::{Borrower, BorrowerRef};
+        use iabs_token_v1::{
+            Error as V1Error, IabsToken as IabsTokenV1, IabsTokenRef as IabsTokenV1Ref,
+        };
+        use ink_e2e::ContractsBackend;
+        use psp22_receiver::{Psp22Receiver, Psp22ReceiverRef};
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        /// Default `min_payment` of `IabsToken::new` (0.01 SBY).
+        const MIN_PAYMENT: Balance = 10_000_000_000_000_000;
+
+        #[ink_e2e::test]
+        async fn mint_checks_payment<Client: E2EBackend>(mut client: Client) -> E2EResult<()> {
+            // given
+            let mut constructor = IabsTokenRef::new();
+            let contract = client
+                .instantiate("iabs_token", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = contract.call_builder::<IabsToken>();
+            let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+
+            let contract_balance = call_builder.contract_balance();
+            let initial_reserve = client
+                .call(&ink_e2e::bob(), &contract_balance)
+                .dry_run()
+                .await?
+                .return_value();
+
+            // when: insufficient payment
+            let mint = call_builder.mint();
+            let insufficient = client
+                .call(&ink_e2e::bob(), &mint)
+                .value(MIN_PAYMENT - 1)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(insufficient, Err(Error::InsufficientPayment));
+
+            // when: exact and excess payment
+            client
+                .call(&ink_e2e::bob(), &mint)
+                .value(MIN_PAYMENT)
+                .submit()
+                .await
+                .expect("mint with exact payment failed");
+            client
+                .call(&ink_e2e::bob(), &mint)
+                .value(MIN_PAYMENT * 3)
+                .submit()
+                .await
+                .expect("mint with excess payment failed");
+
+            // then: each mint credits `mint_amount` and the contract keeps the payment
+            let mint_amount = call_builder.mint_amount();
+            let mint_amount = client
+                .call(&ink_e2e::bob(), &mint_amount)
+                .dry_run()
+                .await?
+                .return_value();
+            let balance_of = call_builder.balance_of(bob);
+            let balance = client
+                .call(&ink_e2e::bob(), &balance_of)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(balance, mint_amount * 2);
+
+            let reserve = client
+                .call(&ink_e2e::bob(), &contract_balance)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(reserve, initial_reserve + MIN_PAYMENT * 4);
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn withdraw_keeps_minimum_balance<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // given
+            let mut constructor = IabsTokenRef::new();
+            let contract = client
+                .instantiate("iabs_token", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = contract.call_builder::<IabsToken>();
+
+            let mint = call_builder.mint();
+            client
+                .call(&ink_e2e::bob(), &mint)
+                .value(MIN_PAYMENT)
+                .submit()
+                .await
+                .expect("mint failed");
+
+            // when
+            let withdraw = call_builder.withdraw();
+            let not_owner = client
+                .call(&ink_e2e::bob(), &withdraw)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(not_owner, Err(Error::NotOwner));
+
+            client
+                .call(&ink_e2e::alice(), &withdraw)
+                .submit()
+                .await
+                .expect("withdraw failed");
+
+            // then: only the existential deposit is left behind
+            let contract_balance = call_builder.contract_balance();
+            let reserve = client
+                .call(&ink_e2e::alice(), &contract_balance)
+                .dry_run()
+                .await?
+                .return_value();
+            let minimum_balance = call_builder.minimum_balance();
+            let minimum_balance = client
+                .call(&ink_e2e::alice(), &minimum_balance)
+                .dry_run()
+                .await?
+                .return_value();
+            assert!(minimum_balance > 0);
+            assert_eq!(reserve, minimum_balance);
+
+            let again = client
+                .call(&ink_e2e::alice(), &withdraw)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(again, Err(Error::NoFundsToWithdraw));
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn setters_are_owner_only<Client: E2EBackend>(mut client: Client) -> E2EResult<()> {
+            // given
+            let mut constructor = IabsTokenRef::new();
+            let contract = client
+                .instantiate("iabs_token", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = contract.call_builder::<IabsToken>();
+
+            // when
+            let set_min_payment = call_builder.set_min_payment(1);
+            let set_mint_amount = call_builder.set_mint_amount(2);
+            let not_owner = client
+                .call(&ink_e2e::bob(), &set_min_payment)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(not_owner, Err(Error::NotOwner));
+            let not_owner = client
+                .call(&ink_e2e::bob(), &set_mint_amount)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(not_owner, Err(Error::NotOwner));
+
+            client
+                .call(&ink_e2e::alice(), &set_min_payment)
+                .submit()
+                .await
+                .expect("set_min_payment failed");
+            client
+                .call(&ink_e2e::alice(), &set_mint_amount)
+                .submit()
+                .await
+                .expect("set_mint_amount failed");
+
+            // then
+            let min_payment = call_builder.min_payment();
+            let min_payment = client
+                .call(&ink_e2e::bob(), &min_payment)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(min_payment, 1);
+            let mint_amount = call_builder.mint_amount();
+            let mint_amount = client
+                .call(&ink_e2e::bob(), &mint_amount)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(mint_amount, 2);
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn transfer_ownership_works<Client: E2EBackend>(mut client: Client) -> E2EResult<()> {
+            // given
+            let mut constructor = IabsTokenRef::new();
+            let contract = client
+                .instantiate("iabs_token", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = contract.call_builder::<IabsToken>();
+            let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+
+            // when
+            let transfer_ownership = call_builder.transfer_ownership(bob);
+            let not_owner = client
+                .call(&ink_e2e::bob(), &transfer_ownership)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(not_owner, Err(Error::NotOwner));
+
+            client
+                .call(&ink_e2e::alice(), &transfer_ownership)
+                .submit()
+                .await
+                .expect("transfer_ownership failed");
+
+            // then
+            let owner = call_builder.owner();
+            let owner = client
+                .call(&ink_e2e::alice(), &owner)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(owner, bob);
+
+            let set_min_payment = call_builder.set_min_payment(1);
+            let previous_owner = client
+                .call(&ink_e2e::alice(), &set_min_payment)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(previous_owner, Err(Error::NotOwner));
+            let new_owner = client
+                .call(&ink_e2e::bob(), &set_min_payment)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(new_owner, Ok(()));
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn transfer_and_call_works<Client: E2EBackend>(mut client: Client) -> E2EResult<()> {
+            // given
+            let mut constructor = IabsTokenRef::new();
+            let contract = client
+                .instantiate("iabs_token", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = contract.call_builder::<IabsToken>();
+            let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+            let charlie = ink_e2e::account_id(ink_e2e::AccountKeyring::Charlie);
+
+            let mint = call_builder.mint();
+            client
+                .call(&ink_e2e::bob(), &mint)
+                .value(MIN_PAYMENT)
+                .submit()
+                .await
+                .expect("mint failed");
+
+            let mut constructor = Psp22ReceiverRef::new(true);
+            let accepting = client
+                .instantiate("psp22_receiver", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate accepting receiver failed");
+            let mut constructor = Psp22ReceiverRef::new(false);
+            let rejecting = client
+                .instantiate("psp22_receiver", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate rejecting receiver failed");
+
+            // when: the receiver accepts
+            let transfer_and_call =
+                call_builder.transfer_and_call(accepting.account_id, 100, vec![1, 2, 3]);
+            client
+                .call(&ink_e2e::bob(), &transfer_and_call)
+                .submit()
+                .await
+                .expect("transfer_and_call to accepting receiver failed");
+
+            // then: the tokens arrived and the hook saw them
+            let balance_of = call_builder.balance_of(accepting.account_id);
+            let balance = client
+                .call(&ink_e2e::bob(), &balance_of)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(balance, 100);
+
+            let receiver = accepting.call_builder::<Psp22Receiver>();
+            let received = receiver.received();
+            let received = client
+                .call(&ink_e2e::bob(), &received)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(received, 100);
+            let last_data = receiver.last_data();
+            let last_data = client
+                .call(&ink_e2e::bob(), &last_data)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(last_data, vec![1, 2, 3]);
+
+            // when: the receiver rejects
+            let balance_of_bob = call_builder.balance_of(bob);
+            let bob_before = client
+                .call(&ink_e2e::bob(), &balance_of_bob)
+                .dry_run()
+                .await?
+                .return_value();
+            let transfer_and_call =
+                call_builder.transfer_and_call(rejecting.account_id, 100, Vec::new());
+            let rejected = client
+                .call(&ink_e2e::bob(), &transfer_and_call)
+                .submit()
+                .await
+                .expect("transfer_and_call to rejecting receiver failed")
+                .return_value();
+            assert_eq!(
+                rejected,
+                Err(Error::SafeTransferCheckFailed(String::from(
+                    "transfers not accepted"
+                )))
+            );
+
+            // then: the transfer was reverted
+            let balance_of = call_builder.balance_of(rejecting.account_id);
+            let balance = client
+                .call(&ink_e2e::bob(), &balance_of)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(balance, 0);
+            let bob_after = client
+                .call(&ink_e2e::bob(), &balance_of_bob)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(bob_after, bob_before);
+
+            // when: the recipient is a plain account
+            let transfer_and_call = call_builder.transfer_and_call(charlie, 100, Vec::new());
+            client
+                .call(&ink_e2e::bob(), &transfer_and_call)
+                .submit()
+                .await
+                .expect("transfer_and_call to plain account failed");
+
+            // then: it behaves like a transfer
+            let balance_of = call_builder.balance_of(charlie);
+            let balance = client
+                .call(&ink_e2e::bob(), &balance_of)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(balance, 100);
+            let bob_after = client
+                .call(&ink_e2e::bob(), &balance_of_bob)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(bob_after, bob_before - 100);
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn flash_loan_works<Client: E2EBackend>(mut client: Client) -> E2EResult<()> {
+            // given: a 1% fee and a borrower holding enough to pay it
+            let mut constructor = IabsTokenRef::new();
+            let contract = client
+                .instantiate("iabs_token", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = contract.call_builder::<IabsToken>();
+            let alice = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let charlie = ink_e2e::account_id(ink_e2e::AccountKeyring::Charlie);
+
+            let mint = call_builder.mint();
+            client
+                .call(&ink_e2e::alice(), &mint)
+                .value(MIN_PAYMENT)
+                .submit()
+                .await
+                .expect("mint failed");
+            let set_flash_fee_bps = call_builder.set_flash_fee_bps(100);
+            client
+                .call(&ink_e2e::alice(), &set_flash_fee_bps)
+                .submit()
+                .await
+                .expect("set_flash_fee_bps failed");
+
+            let mut constructor = BorrowerRef::new(true, 0);
+            let repaying = client
+                .instantiate("flash_borrower", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate repaying borrower failed");
+            let mut constructor = BorrowerRef::new(true, 1);
+            let short = client
+                .instantiate("flash_borrower", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate short borrower failed");
+            let mut constructor = BorrowerRef::new(false, 0);
+            let rejecting = client
+                .instantiate("flash_borrower", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate rejecting borrower failed");
+
+            let transfer = call_builder.transfer(repaying.account_id, 10, Vec::new());
+            client
+                .call(&ink_e2e::alice(), &transfer)
+                .submit()
+                .await
+                .expect("transfer failed");
+
+            // when: the borrower approves the full repayment
+            let flash_loan = call_builder.flash_loan(repaying.account_id, 1_000, Vec::new());
+            client
+                .call(&ink_e2e::bob(), &flash_loan)
+                .submit()
+                .await
+                .expect("flash_loan failed");
+
+            // then: the loan was out during the callback, then burned with the fee paid
+            let borrowed = repaying.call_builder::<Borrower>().borrowed();
+            let borrowed = client
+                .call(&ink_e2e::bob(), &borrowed)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(borrowed, 1_010);
+
+            let balance_of = call_builder.balance_of(repaying.account_id);
+            let balance = client
+                .call(&ink_e2e::bob(), &balance_of)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(balance, 0);
+
+            let balance_of = call_builder.balance_of(alice);
+            let alice_balance = client
+                .call(&ink_e2e::bob(), &balance_of)
+                .dry_run()
+                .await?
+                .return_value();
+            let total_supply = call_builder.total_supply();
+            let supply = client
+                .call(&ink_e2e::bob(), &total_supply)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(alice_balance, supply);
+
+            // when: the borrower approves one token too little
+            let flash_loan = call_builder.flash_loan(short.account_id, 1_000, Vec::new());
+            let result = client
+                .call(&ink_e2e::bob(), &flash_loan)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(result, Err(Error::InsufficientAllowance));
+
+            // when: the borrower rejects the loan
+            let flash_loan = call_builder.flash_loan(rejecting.account_id, 1_000, Vec::new());
+            let result = client
+                .call(&ink_e2e::bob(), &flash_loan)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(
+                result,
+                Err(Error::FlashLoanRejected(String::from("loans not accepted")))
+            );
+
+            // when: the receiver is not a contract
+            let flash_loan = call_builder.flash_loan(charlie, 1_000, Vec::new());
+            let result = client
+                .call(&ink_e2e::bob(), &flash_loan)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(
+                result,
+                Err(Error::FlashLoanRejected(String::from(
+                    "Receiver does not implement FlashBorrower"
+                )))
+            );
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn migrate_lowers_mint_and_balance_of_weight<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // given: a version 1 instance upgraded to this code
+            let mut constructor = IabsTokenV1Ref::new();
+            let contract = client
+                .instantiate("iabs_token_v1", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut v1_call_builder = contract.call_builder::<IabsTokenV1>();
+
+            let set_merkle_root = v1_call_builder.set_merkle_root(Some([0x01; 32]));
+            client
+                .call(&ink_e2e::alice(), &set_merkle_root)
+                .submit()
+                .await
+                .expect("set_merkle_root failed");
+
+            let code_hash = client
+                .upload("iabs_token", &ink_e2e::alice())
+                .submit()
+                .await
+                .expect("upload failed")
+                .code_hash;
+            let upgrade = v1_call_builder.upgrade(code_hash);
+            client
+                .call(&ink_e2e::alice(), &upgrade)
+                .submit()
+                .await
+                .expect("upgrade failed");
+
+            let mut call_builder = ink_e2e::create_call_builder::<IabsToken>(contract.account_id);
+            let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+            let mint = call_builder.mint();
+            let balance_of = call_builder.balance_of(bob);
+            let owner = call_builder.owner();
+            let min_payment = call_builder.min_payment();
+            let mint_amount = call_builder.mint_amount();
+            let total_supply = call_builder.total_supply();
+
+            let owner_before = client.call(&ink_e2e::bob(), &owner).dry_run().await?;
+            let min_payment_before = client.call(&ink_e2e::bob(), &min_payment).dry_run().await?;
+            let mint_amount_before = client.call(&ink_e2e::bob(), &mint_amount).dry_run().await?;
+            let total_supply_before = client
+                .call(&ink_e2e::bob(), &total_supply)
+                .dry_run()
+                .await?;
+            let mint_before = client
+                .call(&ink_e2e::bob(), &mint)
+                .value(MIN_PAYMENT)
+                .dry_run()
+                .await?
+                .exec_result
+                .gas_consumed;
+            let balance_of_before = client
+                .call(&ink_e2e::bob(), &balance_of)
+                .dry_run()
+                .await?
+                .exec_result
+                .gas_consumed;
+
+            // when
+            let migrate = call_builder.migrate();
+            client
+                .call(&ink_e2e::alice(), &migrate)
+                .submit()
+                .await
+                .expect("migrate failed");
+
+            // then: the smaller root cell is cheaper to load and to store back
+            let mint_after = client
+                .call(&ink_e2e::bob(), &mint)
+                .value(MIN_PAYMENT)
+                .dry_run()
+                .await?
+                .exec_result
+                .gas_consumed;
+            let balance_of_after = client
+                .call(&ink_e2e::bob(), &balance_of)
+                .dry_run()
+                .await?
+                .exec_result
+                .gas_consumed;
+
+            assert!(mint_after.ref_time() < mint_before.ref_time());
+            assert!(mint_after.proof_size() < mint_before.proof_size());
+            assert!(balance_of_after.ref_time() < balance_of_before.ref_time());
+            assert!(balance_of_after.proof_size() < balance_of_before.proof_size());
+
+            // the moved fields keep their values and are read from their own cells
+            let owner_after = client.call(&ink_e2e::bob(), &owner).dry_run().await?;
+            let min_payment_after = client.call(&ink_e2e::bob(), &min_payment).dry_run().await?;
+            let mint_amount_after = client.call(&ink_e2e::bob(), &mint_amount).dry_run().await?;
+            let total_supply_after = client
+                .call(&ink_e2e::bob(), &total_supply)
+                .dry_run()
+                .await?;
+
+            for (before, after) in [
+                (&owner_before.exec_result, &owner_after.exec_result),
+                (
+                    &min_payment_before.exec_result,
+                    &min_payment_after.exec_result,
+                ),
+                (
+                    &mint_amount_before.exec_result,
+                    &mint_amount_after.exec_result,
+                ),
+                (
+                    &total_supply_before.exec_result,
+                    &total_supply_after.exec_result,
+                ),
+            ] {
+                assert!(after.gas_consumed.ref_time() < before.gas_consumed.ref_time());
+                assert!(after.gas_consumed.proof_size() < before.gas_consumed.proof_size());
+            }
+
+            assert_eq!(owner_after.return_value(), owner_before.return_value());
+            assert_eq!(
+                min_payment_after.return_value(),
+                min_payment_before.return_value()
+            );
+            assert_eq!(
+                mint_amount_after.return_value(),
+                mint_amount_before.return_value()
+            );
+            assert_eq!(
+                total_supply_after.return_value(),
+                total_supply_before.return_value()
+            );
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn upgrade_keeps_balances<Client: E2EBackend>(mut client: Client) -> E2EResult<()> {
+            // given: a version 1 instance with balances, an allowance and a presale root
+            let mut constructor = IabsTokenV1Ref::new();
+            let contract = client
+                .instantiate("iabs_token_v1", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut v1_call_builder = contract.call_builder::<IabsTokenV1>();
+            let alice = ink_e2e::account_id(ink_e2e::AccountKeyring::Alice);
+            let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+            let charlie = ink_e2e::account_id(ink_e2e::AccountKeyring::Charlie);
+
+            let mint = v1_call_builder.mint();
+            client
+                .call(&ink_e2e::bob(), &mint)
+                .value(MIN_PAYMENT)
+                .submit()
+                .await
+                .expect("mint failed");
+            let approve = v1_call_builder.approve(charlie, 42);
+            client
+                .call(&ink_e2e::bob(), &approve)
+                .submit()
+                .await
+                .expect("approve failed");
+            let set_merkle_root = v1_call_builder.set_merkle_root(Some([0x01; 32]));
+            client
+                .call(&ink_e2e::alice(), &set_merkle_root)
+                .submit()
+                .await
+                .expect("set_merkle_root failed");
+
+            let balance_of = v1_call_builder.balance_of(bob);
+            let minted = client
+                .call(&ink_e2e::bob(), &balance_of)
+                .dry_run()
+                .await?
+                .return_value();
+
+            // when
+            let code_hash = client
+                .upload("iabs_token", &ink_e2e::alice())
+                .submit()
+                .await
+                .expect("upload failed")
+                .code_hash;
+
+            let upgrade = v1_call_builder.upgrade(code_hash);
+            let not_owner = client
+                .call(&ink_e2e::bob(), &upgrade)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(not_owner, Err(V1Error::NotOwner));
+
+            client
+                .call(&ink_e2e::alice(), &upgrade)
+                .submit()
+                .await
+                .expect("upgrade failed");
+
+            // then: the new code reads the version 1 root cell
+            let mut call_builder = ink_e2e::create_call_builder::<IabsToken>(contract.account_id);
+            let storage_version = call_builder.storage_version();
+            let version = client
+                .call(&ink_e2e::bob(), &storage_version)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(version, 1);
+
+            let migrate = call_builder.migrate();
+            let not_owner = client
+                .call(&ink_e2e::bob(), &migrate)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(not_owner, Err(Error::NotOwner));
+
+            client
+                .call(&ink_e2e::alice(), &migrate)
+                .submit()
+                .await
+                .expect("migrate failed");
+
+            // then: state survived the move to the version 2 layout
+            let version = client
+                .call(&ink_e2e::bob(), &storage_version)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(version, STORAGE_VERSION);
+
+            let balance_of = call_builder.balance_of(bob);
+            let balance = client
+                .call(&ink_e2e::bob(), &balance_of)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(balance, minted);
+
+            let total_supply = call_builder.total_supply();
+            let supply = client
+                .call(&ink_e2e::bob(), &total_supply)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(supply, minted);
+
+            let allowance = call_builder.allowance(bob, charlie);
+            let allowed = client
+                .call(&ink_e2e::bob(), &allowance)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(allowed, 42);
+
+            let owner = call_builder.owner();
+            let owner = client
+                .call(&ink_e2e::bob(), &owner)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(owner, alice);
+
+            let token_name = call_builder.token_name();
+            let name = client
+                .call(&ink_e2e::bob(), &token_name)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(name, Some(String::from("IabsToken")));
+
+            let token_symbol = call_builder.token_symbol();
+            let symbol = client
+                .call(&ink_e2e::bob(), &token_symbol)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(symbol, Some(String::from("IABS")));
+
+            let merkle_root = call_builder.merkle_root();
+            let root = client
+                .call(&ink_e2e::bob(), &merkle_root)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(root, Some([0x01; 32]));
+
+            let migrated = client
+                .call(&ink_e2e::alice(), &migrate)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(migrated, Err(Error::NothingToMigrate));
+
+            // minting keeps working on the upgraded instance
+            let mint = call_builder.mint();
+            client
+                .call(&ink_e2e::bob(), &mint)
+                .value(MIN_PAYMENT)
+                .submit()
+                .await
+                .expect("mint after upgrade failed");
+            let balance = client
+                .call(&ink_e2e::bob(), &balance_of)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(balance, minted * 2);
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn upgraded_balances_keep_voting_power<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // given: bob minted on a version 1 instance, before checkpoints existed
+            let mut constructor = IabsTokenV1Ref::new();
+            let contract = client
+                .instantiate("iabs_token_v1", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut v1_call_builder = contract.call_builder::<IabsTokenV1>();
+            let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+
+            let mint = v1_call_builder.mint();
+            client
+                .call(&ink_e2e::bob(), &mint)
+                .value(MIN_PAYMENT)
+                .submit()
+                .await
+                .expect("mint failed");
+            let balance_of = v1_call_builder.balance_of(bob);
+            let minted = client
+                .call(&ink_e2e::bob(), &balance_of)
+                .dry_run()
+                .await?
+                .return_value();
+
+            let code_hash = client
+                .upload("iabs_token", &ink_e2e::alice())
+                .submit()
+                .await
+                .expect("upload failed")
+                .code_hash;
+            let upgrade = v1_call_builder.upgrade(code_hash);
+            client
+                .call(&ink_e2e::alice(), &upgrade)
+                .submit()
+                .await
+                .expect("upgrade failed");
+
+            let mut call_builder = ink_e2e::create_call_builder::<IabsToken>(contract.account_id);
+            let migrate = call_builder.migrate();
+            client
+                .call(&ink_e2e::alice(), &migrate)
+                .submit()
+                .await
+                .expect("migrate failed");
+            let set_governance_config = call_builder.set_governance_config(100, 5_000, 5_000);
+            client
+                .call(&ink_e2e::alice(), &set_governance_config)
+                .submit()
+                .await
+                .expect("set_governance_config failed");
+
+            // when: bob proposes, then moves tokens for the first time after the snapshot
+            let propose = call_builder.propose(ProposalAction::SetMintAmount(42));
+            let id = client
+                .call(&ink_e2e::bob(), &propose)
+                .submit()
+                .await
+                .expect("propose failed")
+                .return_value()
+                .expect("proposal not created");
+            let burn = call_builder.burn(1);
+            client
+                .call(&ink_e2e::bob(), &burn)
+                .submit()
+                .await
+                .expect("burn failed");
+
+            // then: the snapshot still sees the balance and supply minted under version 1
+            let vote = call_builder.vote(id, true);
+            client
+                .call(&ink_e2e::bob(), &vote)
+                .submit()
+                .await
+                .expect("vote failed");
+
+            let proposal = call_builder.proposal(id);
+            let proposal = client
+                .call(&ink_e2e::bob(), &proposal)
+                .dry_run()
+                .await?
+                .return_value()
+                .expect("proposal not found");
+            assert_eq!(proposal.votes_for, minted);
+
+            let total_supply_at = call_builder.total_supply_at(proposal.snapshot_block);
+            let supply = client
+                .call(&ink_e2e::bob(), &total_supply_at)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(supply, minted);
+
+            let balance_of = call_builder.balance_of(bob);
+            let balance = client
+                .call(&ink_e2e::bob(), &balance_of)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(balance, minted - 1);
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn commit_reveal_mint_works<Client: E2EBackend>(mut client: Client) -> E2EResult<()> {
+            // given
+            let mut constructor = IabsTokenRef::new();
+            let contract = client
+                .instantiate("iabs_token", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = contract.call_builder::<IabsToken>();
+            let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+
+            let set_commit_reveal = call_builder.set_commit_reveal(true, 1, 100);
+            client
+                .call(&ink_e2e::alice(), &set_commit_reveal)
+                .submit()
+                .await
+                .expect("set_commit_reveal failed");
+
+            // when
+            let secret = [0x2a; 32];
+            let commitment_hash = call_builder.commitment_hash(bob, secret);
+            let hash = client
+                .call(&ink_e2e::bob(), &commitment_hash)
+                .dry_run()
+                .await?
+                .return_value();
+            let commit = call_builder.commit(hash);
+            client
+                .call(&ink_e2e::bob(), &commit)
+                .value(MIN_PAYMENT)
+                .submit()
+                .await
+                .expect("commit failed");
+
+            let committed_payments = call_builder.committed_payments();
+            let committed = client
+                .call(&ink_e2e::bob(), &committed_payments)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(committed, MIN_PAYMENT);
+
+            let withdraw = call_builder.withdraw();
+            let withdrawn = client
+                .call(&ink_e2e::alice(), &withdraw)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(withdrawn, Err(Error::NoFundsToWithdraw));
+
+            let wrong_reveal = call_builder.reveal([0x2b; 32]);
+            let wrong = client
+                .call(&ink_e2e::bob(), &wrong_reveal)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(wrong, Err(Error::InvalidReveal));
+
+            let reveal = call_builder.reveal(secret);
+            client
+                .call(&ink_e2e::bob(), &reveal)
+                .submit()
+                .await
+                .expect("reveal failed");
+
+            // then
+            let mint_amount = call_builder.mint_amount();
+            let mint_amount = client
+                .call(&ink_e2e::bob(), &mint_amount)
+                .dry_run()
+                .await?
+                .return_value();
+            let balance_of = call_builder.balance_of(bob);
+            let balance = client
+                .call(&ink_e2e::bob(), &balance_of)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(balance, mint_amount);
+
+            let committed = client
+                .call(&ink_e2e::bob(), &committed_payments)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(committed, 0);
+
+            Ok(())
+        }
+
+        #[ink_e2e::test]
+        async fn expired_commitment_is_refunded<Client: E2EBackend>(
+            mut client: Client,
+        ) -> E2EResult<()> {
+            // given
+            let mut constructor = IabsTokenRef::new();
+            let contract = client
+                .instantiate("iabs_token", &ink_e2e::alice(), &mut constructor)
+                .submit()
+                .await
+                .expect("instantiate failed");
+            let mut call_builder = contract.call_builder::<IabsToken>();
+            let bob = ink_e2e::account_id(ink_e2e::AccountKeyring::Bob);
+
+            let set_commit_reveal = call_builder.set_commit_reveal(true, 1, 2);
+            client
+                .call(&ink_e2e::alice(), &set_commit_reveal)
+                .submit()
+                .await
+                .expect("set_commit_reveal failed");
+
+            let secret = [0x2a; 32];
+            let commitment_hash = call_builder.commitment_hash(bob, secret);
+            let hash = client
+                .call(&ink_e2e::bob(), &commitment_hash)
+                .dry_run()
+                .await?
+                .return_value();
+            let commit = call_builder.commit(hash);
+            client
+                .call(&ink_e2e::bob(), &commit)
+                .value(MIN_PAYMENT)
+                .submit()
+                .await
+                .expect("commit failed");
+
+            let commitment = call_builder.commitment(bob);
+            let committed = client
+                .call(&ink_e2e::bob(), &commitment)
+                .dry_run()
+                .await?
+                .return_value()
+                .expect("commitment missing");
+            assert_eq!(committed.reveal_end - committed.reveal_start, 2);
+
+            let contract_balance = call_builder.contract_balance();
+            let reserve = client
+                .call(&ink_e2e::bob(), &contract_balance)
+                .dry_run()
+                .await?
+                .return_value();
+
+            // when: the owner retunes the sale while the commitment is pending
+            let disable_commit_reveal = call_builder.set_commit_reveal(false, 0, 0);
+            client
+                .call(&ink_e2e::alice(), &disable_commit_reveal)
+                .submit()
+                .await
+                .expect("set_commit_reveal failed");
+
+            // then: the commitment keeps the window it was made with
+            let unchanged = client
+                .call(&ink_e2e::bob(), &commitment)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(unchanged, Some(committed));
+
+            let refund_commitment = call_builder.refund_commitment();
+            let early = client
+                .call(&ink_e2e::bob(), &refund_commitment)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(early, Err(Error::CommitmentNotExpired));
+
+            // when: the reveal window lapses
+            for _ in 0..4 {
+                client
+                    .call(&ink_e2e::alice(), &disable_commit_reveal)
+                    .submit()
+                    .await
+                    .expect("set_commit_reveal failed");
+            }
+
+            let reveal = call_builder.reveal(secret);
+            let expired = client
+                .call(&ink_e2e::bob(), &reveal)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(expired, Err(Error::CommitmentExpired));
+
+            client
+                .call(&ink_e2e::bob(), &refund_commitment)
+                .submit()
+                .await
+                .expect("refund_commitment failed");
+
+            // then: the payment went back and no tokens were minted
+            let refunded = client
+                .call(&ink_e2e::bob(), &contract_balance)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(refunded, reserve - MIN_PAYMENT);
+
+            let committed_payments = call_builder.committed_payments();
+            let committed = client
+                .call(&ink_e2e::bob(), &committed_payments)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(committed, 0);
+
+            let gone = client
+                .call(&ink_e2e::bob(), &commitment)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(gone, None);
+
+            let balance_of = call_builder.balance_of(bob);
+            let balance = client
+                .call(&ink_e2e::bob(), &balance_of)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(balance, 0);
+
+            let again = client
+                .call(&ink_e2e::bob(), &refund_commitment)
+                .dry_run()
+                .await?
+                .return_value();
+            assert_eq!(again, Err(Error::NoCommitment));
+
+            Ok(())
         }
     }
 }